@@ -0,0 +1,296 @@
+use std::net::SocketAddr;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use tokio::net::{ToSocketAddrs, UdpSocket, lookup_host};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout_at};
+
+use crate::builder::Config;
+use crate::errors::Error;
+use crate::protocol::{Output, Query, QueryOutcome, Response, Stage};
+use crate::{A2SBuilder, Collector, Engine, MAX_DATAGRAM_SIZE, Quirks, recv_error};
+
+/// Asynchronous counterpart of [`A2S`](crate::A2S), driven by tokio
+pub struct AsyncA2S<A: ToSocketAddrs> {
+    pub(crate) address: A,
+    pub(crate) config: Config,
+    /// Socket connected to the server, created on first use and dropped on I/O errors
+    pub(crate) socket: Mutex<Option<UdpSocket>>,
+    /// App ID of the server, cached from the last info response
+    pub(crate) app_id: std::sync::Mutex<Option<u32>>,
+    /// Address of the server last connected to, kept for the context of errors
+    pub(crate) last_peer: std::sync::Mutex<Option<SocketAddr>>,
+}
+
+impl<A: ToSocketAddrs> AsyncA2S<A> {
+    pub fn new(address: A) -> Self {
+        AsyncA2S {
+            address,
+            config: Config::default(),
+            socket: Mutex::new(None),
+            app_id: std::sync::Mutex::new(None),
+            last_peer: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Create a builder to configure timeouts and retries, finished with
+    /// [`A2SBuilder::build_async`]
+    pub fn builder(address: A) -> A2SBuilder<A> {
+        A2SBuilder::new(address)
+    }
+
+    /// Set the engine of the server, used to reassemble split responses
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.config.engine = engine;
+        self
+    }
+
+    /// Set the quirks of the server, instead of looking them up by its app ID
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.config.quirks = Some(quirks);
+        self
+    }
+
+    /// Return what could be parsed of malformed responses instead of failing,
+    /// recording the problems as [`QueryOutcome::warnings`]
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.config.lenient = lenient;
+        self
+    }
+
+    /// Send queries from the given socket, connecting it to the server. If the
+    /// socket has to be recreated after an error, its local address is bound again.
+    pub async fn with_socket(mut self, socket: UdpSocket) -> Result<Self, Error> {
+        let local = socket.local_addr()?;
+        let mut last_error = None;
+
+        for address in self.resolve().await? {
+            if address.is_ipv4() != local.is_ipv4() {
+                continue;
+            }
+            match socket.connect(address).await {
+                Ok(()) => {
+                    self.config.bind_like(local);
+                    self.socket = Mutex::new(Some(socket));
+                    return Ok(self);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable))
+            .into())
+    }
+
+    /// App ID of the server, known once its info has been fetched
+    pub fn app_id(&self) -> Option<u32> {
+        *self.app_id.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run the query to completion, caching the app ID of the server once known
+    pub(crate) async fn execute(
+        &self,
+        mut query: Query,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Response>, Error> {
+        let mut request = query.request().to_vec();
+
+        let res = loop {
+            match self.send_and_recv(&mut query, &request, deadline).await {
+                Ok(Output::Send(next)) => request = next,
+                Ok(Output::Done(response)) => {
+                    break Ok(query.outcome(response, self.last_peer().await));
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        if let Some(app_id) = query.app_id() {
+            *self.app_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(app_id);
+        }
+        let peer = self.last_peer().await;
+        res.map_err(|e| e.with_context(Some(query.kind()), query.stage(), peer))
+    }
+
+    /// Send the request, sending it again if the server does not answer in time
+    pub(crate) async fn send_and_recv<C: Collector>(
+        &self,
+        collector: &mut C,
+        data: &[u8],
+        deadline: Option<Instant>,
+    ) -> Result<C::Output, Error> {
+        let mut attempt = 0;
+        loop {
+            let timeout = self.config.attempt_timeout(deadline)?;
+            match self.send_and_recv_once(collector, data, timeout).await {
+                Err(e) if e.is_timeout() && attempt < self.config.retries => {
+                    attempt += 1;
+                    sleep(self.config.backoff_delay(attempt, deadline)).await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn send_and_recv_once<C: Collector>(
+        &self,
+        collector: &mut C,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<C::Output, Error> {
+        let mut socket = self.socket.lock().await;
+
+        let res = match socket.as_ref() {
+            Some(connected) => match discard_pending(connected) {
+                Ok(()) => self.exchange(connected, collector, data, timeout).await,
+                Err(e) => Err(e),
+            },
+            None => {
+                self.connect_and_exchange(&mut socket, collector, data, timeout)
+                    .await
+            }
+        };
+        if matches!(&res, Err(e @ Error::Io(_)) if !e.is_timeout()) {
+            *socket = None;
+        }
+        res
+    }
+
+    /// Try each resolved address of the server in turn, sharing the timeout between
+    /// them, and keep the socket of the first one answering
+    async fn connect_and_exchange<C: Collector>(
+        &self,
+        socket: &mut Option<UdpSocket>,
+        collector: &mut C,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<C::Output, Error> {
+        let addresses = self
+            .resolve()
+            .await
+            .map_err(|e| e.with_context(None, Stage::Connect, None))?;
+        let timeout_at = Instant::now() + timeout;
+        let mut last_error = None;
+
+        for (i, address) in addresses.iter().enumerate() {
+            let remaining = timeout_at.saturating_duration_since(Instant::now());
+            let address_timeout = remaining / (addresses.len() - i) as u32;
+
+            let connected = match connect(*address, &self.config).await {
+                Ok(connected) => {
+                    *self
+                        .last_peer
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner) = Some(*address);
+                    connected
+                }
+                Err(e) => {
+                    last_error = Some(e.with_context(None, Stage::Connect, Some(*address)));
+                    continue;
+                }
+            };
+            match self
+                .exchange(&connected, collector, data, address_timeout)
+                .await
+            {
+                Err(e) if e.is_network() => last_error = Some(e),
+                res => {
+                    *socket = Some(connected);
+                    return res;
+                }
+            }
+        }
+
+        Err(last_error.expect("at least one address is resolved"))
+    }
+
+    async fn exchange<C: Collector>(
+        &self,
+        socket: &UdpSocket,
+        collector: &mut C,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<C::Output, Error> {
+        let timeout_at_instant = tokio::time::Instant::now() + timeout;
+        collector.reset();
+        for request in collector.requests(data) {
+            socket.send(request).await?;
+        }
+
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+
+        loop {
+            let n_received = timeout_at(timeout_at_instant, socket.recv(&mut buf))
+                .await
+                .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()))
+                .map_err(|e| recv_error(collector, e))?;
+            if let Some(res) = collector.push(&buf[..n_received])? {
+                return Ok(res);
+            }
+        }
+    }
+
+    /// Resolve every address of the server
+    pub(crate) async fn resolve(&self) -> Result<Vec<SocketAddr>, Error> {
+        let addresses: Vec<SocketAddr> = lookup_host(&self.address).await?.collect();
+        if addresses.is_empty() {
+            return Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable).into());
+        }
+        Ok(addresses)
+    }
+
+    /// Address of the server which answered, once a query succeeded
+    pub async fn peer_addr(&self) -> Option<SocketAddr> {
+        self.socket
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|socket| socket.peer_addr().ok())
+    }
+
+    /// Address of the server currently or last connected to, even if its
+    /// socket was dropped after an error
+    pub(crate) async fn last_peer(&self) -> Option<SocketAddr> {
+        let last_peer = *self
+            .last_peer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.peer_addr().await.or(last_peer)
+    }
+}
+
+/// Bind a socket on the configured local address and connect it to the server
+pub(crate) async fn connect(address: SocketAddr, config: &Config) -> Result<UdpSocket, Error> {
+    let mut last_error = None;
+
+    for local in config.local_addrs(address)? {
+        match UdpSocket::bind(local).await {
+            Ok(socket) => {
+                socket.connect(address).await?;
+                return Ok(socket);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => last_error = Some(e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(last_error
+        .expect("at least one local address is tried")
+        .into())
+}
+
+/// Drop datagrams left over from previous requests, such as late answers to a
+/// timed out attempt
+pub(crate) fn discard_pending(socket: &UdpSocket) -> Result<(), Error> {
+    let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+
+    loop {
+        match socket.try_recv(&mut buf) {
+            Ok(_) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
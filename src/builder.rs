@@ -0,0 +1,256 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use crate::errors::Error;
+use crate::protocol::{Query, QueryKind};
+use crate::utils::random::random_u64;
+use crate::{A2S, Engine, Quirks};
+
+#[cfg(feature = "tokio")]
+use crate::AsyncA2S;
+#[cfg(feature = "tokio")]
+use std::sync::Mutex;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(200);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Settings shared by the blocking and asynchronous clients
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub(crate) engine: Engine,
+    pub(crate) quirks: Option<Quirks>,
+    pub(crate) timeout: Duration,
+    pub(crate) retries: u32,
+    pub(crate) backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) deadline: Option<Duration>,
+    pub(crate) local_ip: Option<IpAddr>,
+    pub(crate) local_ports: Option<RangeInclusive<u16>>,
+    pub(crate) lenient: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            engine: Engine::default(),
+            quirks: None,
+            timeout: DEFAULT_TIMEOUT,
+            retries: 0,
+            backoff: DEFAULT_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            deadline: None,
+            local_ip: None,
+            local_ports: None,
+            lenient: false,
+        }
+    }
+}
+
+impl Config {
+    /// Instant at which a query started now must be over
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.deadline.map(|deadline| Instant::now() + deadline)
+    }
+
+    /// Start a query with these settings, to a server running the given app ID if known
+    pub(crate) fn query(&self, kind: QueryKind, app_id: Option<u32>) -> Query {
+        let mut query = Query::new(kind, self.engine).with_lenient(self.lenient);
+        if let Some(quirks) = self.quirks {
+            query = query.with_quirks(quirks);
+        }
+        match app_id {
+            Some(app_id) => query.with_app_id(app_id),
+            None => query,
+        }
+    }
+
+    /// Quirks of a server running the given app ID, unless they were set explicitly
+    pub(crate) fn quirks(&self, app_id: Option<u32>) -> Quirks {
+        Quirks::resolve(self.quirks, app_id)
+    }
+
+    /// Time to wait for an answer, shortened so that the deadline is respected
+    pub(crate) fn attempt_timeout(&self, deadline: Option<Instant>) -> Result<Duration, Error> {
+        let Some(deadline) = deadline else {
+            return Ok(self.timeout);
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into());
+        }
+        Ok(self.timeout.min(remaining))
+    }
+
+    /// Delay before the given retry, doubling at each attempt up to the maximum backoff
+    /// with up to 50% of jitter, and shortened so that the deadline is respected
+    pub(crate) fn backoff_delay(&self, attempt: u32, deadline: Option<Instant>) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let jitter = (random_u64() % 1000) as f64 / 1000.0;
+        let delay = delay.saturating_sub((delay / 2).mul_f64(jitter));
+
+        match deadline {
+            Some(deadline) => delay.min(deadline.saturating_duration_since(Instant::now())),
+            None => delay,
+        }
+    }
+
+    /// Local addresses to bind a socket talking to the given server, trying each
+    /// port of the configured range starting from a random one
+    pub(crate) fn local_addrs(&self, remote: SocketAddr) -> Result<Vec<SocketAddr>, Error> {
+        let ip = match (self.local_ip, remote) {
+            (None, SocketAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (None, SocketAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            (Some(ip), remote) if ip.is_ipv4() == remote.is_ipv4() => ip,
+            (Some(_), _) => {
+                return Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable).into());
+            }
+        };
+
+        let Some(ports) = self.local_ports.clone().filter(|ports| !ports.is_empty()) else {
+            return Ok(vec![SocketAddr::new(ip, 0)]);
+        };
+
+        let len = (*ports.end() - *ports.start()) as u64 + 1;
+        let offset = random_u64() % len;
+        Ok((0..len)
+            .map(|i| *ports.start() + ((offset + i) % len) as u16)
+            .map(|port| SocketAddr::new(ip, port))
+            .collect())
+    }
+
+    /// Bind to the local address of a caller supplied socket when reconnecting
+    pub(crate) fn bind_like(&mut self, local: SocketAddr) {
+        if !local.ip().is_unspecified() {
+            self.local_ip = Some(local.ip());
+        }
+        if local.port() != 0 {
+            self.local_ports = Some(local.port()..=local.port());
+        }
+    }
+}
+
+/// Builder for [`A2S`], configuring timeouts and retries
+pub struct A2SBuilder<A> {
+    address: A,
+    config: Config,
+}
+
+impl<A> A2SBuilder<A> {
+    pub(crate) fn new(address: A) -> Self {
+        A2SBuilder {
+            address,
+            config: Config::default(),
+        }
+    }
+
+    /// Engine of the server, used to reassemble split responses
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.config.engine = engine;
+        self
+    }
+
+    /// Quirks of the server, instead of looking them up by its app ID
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.config.quirks = Some(quirks);
+        self
+    }
+
+    /// Time to wait for each answer of the server (5 seconds by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Number of times a request is sent again when the server does not answer in time
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    /// Base delay before retrying, doubled after each failed attempt
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.config.backoff = backoff;
+        self
+    }
+
+    /// Longest delay before retrying, however many attempts failed (5 seconds by default)
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.config.max_backoff = max_backoff;
+        self
+    }
+
+    /// Maximum duration of a whole query, including challenges and retries
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Local address to send queries from, instead of any interface
+    pub fn local_address(mut self, ip: IpAddr) -> Self {
+        self.config.local_ip = Some(ip);
+        self
+    }
+
+    /// Range of local ports to send queries from, instead of an ephemeral port
+    pub fn local_port_range(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.config.local_ports = Some(ports);
+        self
+    }
+
+    /// Return what could be parsed of malformed responses instead of failing,
+    /// recording the problems as warnings
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.config.lenient = lenient;
+        self
+    }
+}
+
+impl<A: ToSocketAddrs> A2SBuilder<A> {
+    pub fn build(self) -> A2S<A> {
+        A2S {
+            config: self.config,
+            ..A2S::new(self.address)
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<A: tokio::net::ToSocketAddrs> A2SBuilder<A> {
+    pub fn build_async(self) -> AsyncA2S<A> {
+        AsyncA2S {
+            address: self.address,
+            config: self.config,
+            socket: tokio::sync::Mutex::new(None),
+            app_id: Mutex::new(None),
+            last_peer: Mutex::new(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay_is_capped() {
+        let config = Config {
+            backoff: Duration::MAX,
+            ..Config::default()
+        };
+        for attempt in [1, 20, u32::MAX] {
+            let delay = config.backoff_delay(attempt, None);
+            assert!(delay <= DEFAULT_MAX_BACKOFF);
+            assert!(delay >= DEFAULT_MAX_BACKOFF / 2);
+        }
+
+        let config = Config::default();
+        assert!(config.backoff_delay(1, None) <= DEFAULT_BACKOFF);
+        assert!(config.backoff_delay(20, None) <= DEFAULT_MAX_BACKOFF);
+    }
+}
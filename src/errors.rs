@@ -1,25 +1,164 @@
+use std::fmt;
+use std::net::SocketAddr;
+
 use thiserror::Error;
 
+use crate::protocol::{QueryKind, Stage};
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Invalid server response")]
     InvalidResponse,
 
+    #[deprecated(note = "never returned, every query is implemented")]
     #[error("Not implemented: {feature}")]
     NotImplemented { feature: &'static str },
 
     #[error("Invalid header: expected 0x{expected:02X}, found 0x{found:02X}")]
     InvalidHeader { expected: u8, found: u8 },
 
+    #[deprecated(note = "never returned, packets of another response are ignored")]
     #[error("Unexpected answer id: expected {expected}, found {found}")]
     UnexpectedAnswerID { expected: i32, found: i32 },
 
+    #[error("Split response of {total} packets exceeds the maximum of {max}")]
+    TooManyPackets { total: u8, max: u8 },
+
+    #[error("Compressed response announces {size} bytes, more than the maximum of {max}")]
+    ResponseTooLarge { size: u32, max: u32 },
+
+    #[error("Decompressed payload does not match its size or checksum")]
+    ChecksumMismatch,
+
+    #[deprecated(note = "never returned, unknown types are kept as `ServerType::Unknown`")]
     #[error("Invalid server type received")]
     InvalidServerType,
 
+    #[deprecated(
+        note = "never returned, unknown environments are kept as `ServerEnvironment::Unknown`"
+    )]
     #[error("Invalid server environment received")]
     InvalidServerEnvironment,
 
+    #[error("Invalid SteamID: {0}")]
+    InvalidSteamId(String),
+
+    /// A request received by a [`Responder`](crate::server::Responder) is not an A2S query
+    #[error("Invalid client request")]
+    InvalidRequest,
+
+    /// The server did not answer in time
+    #[error("Server did not answer in time")]
+    Timeout,
+
+    /// The response ended in the middle of a field
+    #[error("Truncated response: {needed} bytes needed, {available} available")]
+    Truncated { needed: usize, available: usize },
+
+    /// The server kept answering with a new challenge instead of the response
+    #[error("Server kept answering with a new challenge")]
+    ChallengeFailed,
+
+    /// A packet of a split response never arrived, numbered from 0
+    #[error("Packet {number} of a split response of {total} packets never arrived")]
+    FragmentMissing { number: u8, total: u8 },
+
     #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(#[source] std::io::Error),
+
+    /// Error of a query, with the context it happened in
+    #[error("{context}: {source}")]
+    Context {
+        context: Context,
+        source: Box<Error>,
+    },
+}
+
+/// Where a query failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    /// Kind of query, unless the server was being pinged
+    pub kind: Option<QueryKind>,
+
+    /// Stage the query failed at
+    pub stage: Stage,
+
+    /// Address of the server, once resolved
+    pub peer: Option<SocketAddr>,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{kind:?} query")?,
+            None => write!(f, "Ping")?,
+        }
+        if let Some(peer) = self.peer {
+            write!(f, " to {peer}")?;
+        }
+        write!(f, " failed at the {:?} stage", self.stage)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::Io(e),
+        }
+    }
+}
+
+impl Error {
+    /// Whether the error comes from the server not answering in time, fully or partly
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.root(), Error::Timeout | Error::FragmentMissing { .. })
+    }
+
+    /// Context of the query the error happened in
+    pub fn context(&self) -> Option<&Context> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Error without the context of the query it happened in
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Attach the context of a query, completing the one already attached if any
+    pub(crate) fn with_context(
+        self,
+        kind: Option<QueryKind>,
+        stage: Stage,
+        peer: Option<SocketAddr>,
+    ) -> Self {
+        match self {
+            Error::Context {
+                mut context,
+                source,
+            } => {
+                context.kind = context.kind.or(kind);
+                context.peer = context.peer.or(peer);
+                Error::Context { context, source }
+            }
+            source => Error::Context {
+                context: Context { kind, stage, peer },
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Whether the error comes from the network rather than from the answer of the server
+    pub(crate) fn is_network(&self) -> bool {
+        matches!(
+            self,
+            Error::Io(_) | Error::Timeout | Error::FragmentMissing { .. }
+        )
+    }
 }
@@ -0,0 +1,356 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::builder::Config;
+use crate::errors::Error;
+use crate::lenient::Warning;
+use crate::protocol::{Output, Query, Response};
+use crate::{Engine, MAX_DATAGRAM_SIZE, Quirks};
+
+/// Longest wait on one socket while the other may receive answers
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub use crate::protocol::QueryKind;
+
+/// Parsed answer to a fleet query
+pub type FleetResponse = Response;
+
+/// Outcome of a query sent to one server of the fleet
+#[derive(Debug)]
+pub struct FleetResult {
+    /// Address of the queried server
+    pub address: SocketAddr,
+
+    /// Kind of query that was sent
+    pub kind: QueryKind,
+
+    /// Answer of the server, or the reason why the query failed
+    pub result: Result<FleetResponse, Error>,
+
+    /// Problems found in the responses of the server, in lenient mode
+    pub warnings: Vec<Warning>,
+}
+
+/// A query in progress with one server
+struct Job {
+    query: Query,
+    deadline: Option<Instant>,
+    timeout_at: Instant,
+    attempts: u32,
+    /// Whether the request is sent again at `timeout_at`, once the backoff is over
+    backoff: bool,
+    /// Failure to send the request, reported on the next timeout check
+    error: Option<Error>,
+}
+
+/// Queries many servers concurrently from a single UDP socket per address family.
+///
+/// Queries to the same server are sent one after the other, so that challenges
+/// and split packets can be matched with the right request. Results are yielded
+/// by iterating over the fleet, in the order they arrive.
+pub struct FleetQuery {
+    /// Sockets queries are sent from, one per address family, bound when first needed
+    sockets: Vec<UdpSocket>,
+    config: Config,
+    /// Queries per server, the first one being in progress
+    jobs: HashMap<SocketAddr, VecDeque<Job>>,
+    /// App ID of servers whose info was received, used to parse players
+    app_ids: HashMap<SocketAddr, u32>,
+    /// Buffer datagrams are received into
+    buf: Vec<u8>,
+}
+
+impl FleetQuery {
+    /// Create a fleet sending queries from sockets bound on any interface, an
+    /// IPv4 or IPv6 one being bound when a server of that family is added
+    pub fn new() -> Self {
+        Self::with_sockets(Vec::new())
+    }
+
+    /// Create a fleet sending queries from the given socket, which must not be connected.
+    /// Servers of the other address family are queried from a socket bound on any interface.
+    pub fn with_socket(socket: UdpSocket) -> Self {
+        Self::with_sockets(vec![socket])
+    }
+
+    fn with_sockets(sockets: Vec<UdpSocket>) -> Self {
+        FleetQuery {
+            sockets,
+            config: Config::default(),
+            jobs: HashMap::new(),
+            app_ids: HashMap::new(),
+            buf: vec![0u8; MAX_DATAGRAM_SIZE],
+        }
+    }
+
+    /// Set the engine of the servers, used to reassemble split responses
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.config.engine = engine;
+        self
+    }
+
+    /// Set the quirks of the servers, instead of looking them up by their app ID
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.config.quirks = Some(quirks);
+        self
+    }
+
+    /// Return what could be parsed of malformed responses instead of failing,
+    /// recording the problems as [`FleetResult::warnings`]
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.config.lenient = lenient;
+        self
+    }
+
+    /// Set the time to wait for each answer of a server (5 seconds by default)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Set the number of times a request is sent again when a server does not answer in time
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    /// Set the base delay before retrying, doubled after each failed attempt
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.config.backoff = backoff;
+        self
+    }
+
+    /// Set the longest delay before retrying (5 seconds by default)
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.config.max_backoff = max_backoff;
+        self
+    }
+
+    /// Set the maximum duration of a query to one server, including challenges and retries
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.config.deadline = Some(deadline);
+        self
+    }
+
+    /// Queue a query to the given server, sending it right away if no other
+    /// query to that server is in progress
+    pub fn add<A: ToSocketAddrs>(&mut self, address: A, kind: QueryKind) -> Result<(), Error> {
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable))?;
+
+        if socket_for(&self.sockets, address).is_none() {
+            let any: SocketAddr = match address {
+                SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+            };
+            self.sockets.push(UdpSocket::bind(any)?);
+        }
+
+        // The query is built again when started, once the app ID may be known
+        let job = Job {
+            query: self.config.query(kind, None),
+            deadline: None,
+            timeout_at: Instant::now(),
+            attempts: 0,
+            backoff: false,
+            error: None,
+        };
+
+        let queue = self.jobs.entry(address).or_default();
+        queue.push_back(job);
+        if queue.len() == 1
+            && let Err(e) = self.start(address)
+        {
+            self.jobs.remove(&address);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Number of queries which did not complete yet
+    pub fn pending(&self) -> usize {
+        self.jobs.values().map(VecDeque::len).sum()
+    }
+
+    /// Send the first queued query to the server, parsing players with the app ID
+    /// received by an earlier info query
+    fn start(&mut self, address: SocketAddr) -> Result<(), Error> {
+        let deadline = self.config.deadline();
+        let app_id = self.app_ids.get(&address).copied();
+        if let Some(job) = self.jobs.get_mut(&address).and_then(VecDeque::front_mut) {
+            job.query = self.config.query(job.query.kind(), app_id);
+            job.deadline = deadline;
+        }
+        self.send(address)
+    }
+
+    /// Send the current request of the query in progress with the server
+    fn send(&mut self, address: SocketAddr) -> Result<(), Error> {
+        let Some(job) = self.jobs.get_mut(&address).and_then(VecDeque::front_mut) else {
+            return Ok(());
+        };
+
+        job.timeout_at = Instant::now() + self.config.attempt_timeout(job.deadline)?;
+        job.backoff = false;
+        let socket = socket_for(&self.sockets, address)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable))?;
+        socket.send_to(job.query.request(), address)?;
+        Ok(())
+    }
+
+    /// Remove the query in progress with the server and start the next one
+    fn finish(&mut self, address: SocketAddr, result: Result<FleetResponse, Error>) -> FleetResult {
+        let queue = self
+            .jobs
+            .get_mut(&address)
+            .expect("finished job must be queued");
+        let job = queue.pop_front().expect("finished job must be queued");
+
+        if queue.is_empty() {
+            self.jobs.remove(&address);
+        } else if let Err(e) = self.start(address) {
+            // The next query can only fail once the current result is returned
+            if let Some(next) = self.jobs.get_mut(&address).and_then(VecDeque::front_mut) {
+                next.timeout_at = Instant::now();
+                next.error = Some(e);
+            }
+        }
+
+        let (kind, stage) = (job.query.kind(), job.query.stage());
+        FleetResult {
+            address,
+            kind,
+            result: result.map_err(|e| e.with_context(Some(kind), stage, Some(address))),
+            warnings: job.query.warnings().to_vec(),
+        }
+    }
+
+    /// Feed a datagram received from the server, returning a result if its query is complete
+    fn receive(&mut self, address: SocketAddr, datagram: &[u8]) -> Option<FleetResult> {
+        let job = self.jobs.get_mut(&address)?.front_mut()?;
+
+        let res = job.query.handle(datagram);
+        if let Some(app_id) = job.query.app_id() {
+            self.app_ids.insert(address, app_id);
+        }
+
+        match res {
+            Ok(Some(Output::Send(_))) => match self.send(address) {
+                Ok(()) => None,
+                Err(e) => Some(self.finish(address, Err(e))),
+            },
+            Ok(Some(Output::Done(response))) => Some(self.finish(address, Ok(response))),
+            Ok(None) => None,
+            Err(e) => Some(self.finish(address, Err(e))),
+        }
+    }
+
+    /// Resend or fail the queries whose server did not answer in time
+    fn handle_timeouts(&mut self) -> Option<FleetResult> {
+        let now = Instant::now();
+        let expired: Vec<SocketAddr> = self
+            .jobs
+            .iter()
+            .filter(|(_, queue)| queue.front().is_some_and(|job| job.timeout_at <= now))
+            .map(|(&address, _)| address)
+            .collect();
+
+        for address in expired {
+            let job = self.jobs.get_mut(&address)?.front_mut()?;
+            if let Some(e) = job.error.take() {
+                return Some(self.finish(address, Err(e)));
+            }
+            if job.backoff {
+                if let Err(e) = self.send(address) {
+                    return Some(self.finish(address, Err(e)));
+                }
+                continue;
+            }
+
+            let can_retry = job.attempts < self.config.retries
+                && job.deadline.is_none_or(|deadline| deadline > now);
+
+            if can_retry {
+                job.attempts += 1;
+                job.timeout_at = now + self.config.backoff_delay(job.attempts, job.deadline);
+                job.backoff = true;
+            } else {
+                let timeout = job.query.timeout_error();
+                return Some(self.finish(address, Err(timeout)));
+            }
+        }
+
+        None
+    }
+
+    /// Receive datagrams into the buffer until a query completes
+    fn wait_result(&mut self, buf: &mut [u8]) -> Option<FleetResult> {
+        loop {
+            if let Some(result) = self.handle_timeouts() {
+                return Some(result);
+            }
+
+            let next_timeout = self
+                .jobs
+                .values()
+                .filter_map(VecDeque::front)
+                .map(|job| job.timeout_at)
+                .min()?;
+
+            let mut wait = next_timeout.saturating_duration_since(Instant::now());
+            if wait.is_zero() {
+                continue;
+            }
+            // With a socket per address family, each is waited on in turn
+            if self.sockets.len() > 1 {
+                wait = wait.min(POLL_INTERVAL);
+            }
+
+            for index in 0..self.sockets.len() {
+                let socket = &self.sockets[index];
+                if socket.set_read_timeout(Some(wait)).is_err() {
+                    continue;
+                }
+
+                // Receive errors (such as ICMP unreachable reports on some platforms) cannot
+                // be matched with a server, so they are left to surface as timeouts
+                if let Ok((n_received, address)) = socket.recv_from(buf)
+                    && let Some(result) = self.receive(address, &buf[..n_received])
+                {
+                    return Some(result);
+                }
+            }
+        }
+    }
+}
+
+impl Default for FleetQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for FleetQuery {
+    type Item = FleetResult;
+
+    /// Wait for the next query to complete, returning `None` once all are done
+    fn next(&mut self) -> Option<FleetResult> {
+        // The buffer is taken out while datagrams received into it are handled
+        let mut buf = std::mem::take(&mut self.buf);
+        let result = self.wait_result(&mut buf);
+        self.buf = buf;
+        result
+    }
+}
+
+/// Socket of the same address family as the server
+fn socket_for(sockets: &[UdpSocket], address: SocketAddr) -> Option<&UdpSocket> {
+    sockets.iter().find(|socket| {
+        socket
+            .local_addr()
+            .is_ok_and(|local| local.is_ipv4() == address.is_ipv4())
+    })
+}
@@ -1,20 +1,38 @@
-use byteorder::{LittleEndian, ReadBytesExt};
-use serde::Serialize;
-use std::io::{Cursor, Read};
+use byteorder::LittleEndian;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
 use std::net::ToSocketAddrs;
+use std::time::Instant;
 
 use crate::errors::Error;
+use crate::lenient::{Parser, Warning, is_exhausted};
+use crate::protocol::{QueryKind, QueryOutcome, Response};
+use crate::steam::{GameId, SteamId};
+use crate::transport::Transport;
+use crate::utils::read_bytes::ReadBytes;
 use crate::utils::read_string::ReadString;
-use crate::{A2S, HEADER_SINGLE_PACKET, S2C_CHALLENGE};
+use crate::utils::write_string::WriteString;
+use crate::{A2S, HEADER_SINGLE_PACKET, single_packet_payload};
+
+#[cfg(feature = "tokio")]
+use crate::AsyncA2S;
 
 // A2S_INFO
-const INFO_REQUEST_HEADER: u8 = 0x54;
-const INFO_REQUEST_PAYLOAD: &str = "Source Engine Query\0";
-const INFO_RESPONSE_HEADER: u8 = 0x49;
+pub(crate) const INFO_REQUEST_HEADER: u8 = 0x54;
+pub(crate) const INFO_REQUEST_PAYLOAD: &str = "Source Engine Query\0";
+pub(crate) const INFO_RESPONSE_HEADER: u8 = 0x49;
+pub(crate) const GOLDSRC_INFO_RESPONSE_HEADER: u8 = 0x6D;
+
+const THE_SHIP_APP_ID: u32 = 2400;
 
-const THE_SHIP_APP_ID: i16 = 2400;
+// Extra data flags, telling which optional fields follow the version
+const EDF_PORT: u8 = 0x80;
+const EDF_STEAM_ID: u8 = 0x10;
+const EDF_SOURCETV: u8 = 0x40;
+const EDF_KEYWORDS: u8 = 0x20;
+const EDF_GAME_ID: u8 = 0x01;
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Info {
     /// Protocol version used by the server.
     pub protocol: u8,
@@ -31,8 +49,9 @@ pub struct Info {
     /// Full name of the game
     pub game: String,
 
-    /// Steam Application ID of game
-    pub app_id: i16,
+    /// Steam Application ID of game, recovered from the GameID if it does not fit
+    /// in the 16 bits sent for it
+    pub app_id: u32,
 
     /// Number of players on the server
     pub players: u8,
@@ -66,11 +85,11 @@ pub struct Info {
 
     /// The server's game port number
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub port: Option<i16>,
+    pub port: Option<u16>,
 
     /// Server's SteamID
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub steam_id: Option<u64>,
+    pub steam_id: Option<SteamId>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sourcetv_info: Option<SourceTV>,
@@ -81,46 +100,122 @@ pub struct Info {
 
     /// The server's 64-bit GameID
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub game_id: Option<u64>,
+    pub game_id: Option<GameId>,
+
+    /// Additional info only sent by obsolete GoldSource servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goldsrc: Option<GoldSrcInfo>,
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ServerType {
     Dedicated,
     NonDedicated,
     SourceTVProxy,
+    /// Raw byte of a server type this crate does not know
+    Unknown(u8),
 }
 
 impl ServerType {
-    fn from_u8(byte: u8) -> Result<Self, Error> {
+    fn from_u8(byte: u8) -> Self {
         match byte {
-            b'd' => Ok(Self::Dedicated),
-            b'l' => Ok(Self::NonDedicated),
-            b'p' => Ok(Self::SourceTVProxy),
-            _ => Err(Error::InvalidServerType),
+            b'd' => Self::Dedicated,
+            b'l' => Self::NonDedicated,
+            b'p' => Self::SourceTVProxy,
+            _ => Self::Unknown(byte),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Dedicated => b'd',
+            Self::NonDedicated => b'l',
+            Self::SourceTVProxy => b'p',
+            Self::Unknown(byte) => byte,
+        }
+    }
+
+    /// Parse the server type, warning about unknown ones in lenient mode
+    fn parse(byte: u8, parser: &mut Parser) -> Self {
+        let server_type = Self::from_u8(byte);
+        if let Self::Unknown(byte) = server_type {
+            parser.warn(Warning::UnknownServerType(byte));
+        }
+        server_type
+    }
+
+    /// Byte of the server type in a GoldSource response, in uppercase
+    fn to_goldsrc_u8(self) -> u8 {
+        match self {
+            Self::Unknown(byte) => byte,
+            server_type => server_type.to_u8().to_ascii_uppercase(),
+        }
+    }
+
+    /// Parse the server type of a GoldSource response, sent in uppercase
+    fn parse_goldsrc(byte: u8, parser: &mut Parser) -> Self {
+        match Self::parse(byte.to_ascii_lowercase(), parser) {
+            Self::Unknown(_) => Self::Unknown(byte),
+            server_type => server_type,
         }
     }
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ServerEnvironment {
     Linux,
     Windows,
     Mac,
+    /// Raw byte of an operating system this crate does not know
+    Unknown(u8),
 }
 
 impl ServerEnvironment {
-    fn from_u8(byte: u8) -> Result<Self, Error> {
+    fn from_u8(byte: u8) -> Self {
         match byte {
-            b'l' => Ok(Self::Linux),
-            b'w' => Ok(Self::Windows),
-            b'm' | b'o' => Ok(Self::Mac),
-            _ => Err(Error::InvalidServerEnvironment),
+            b'l' => Self::Linux,
+            b'w' => Self::Windows,
+            b'm' | b'o' => Self::Mac,
+            _ => Self::Unknown(byte),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Linux => b'l',
+            Self::Windows => b'w',
+            Self::Mac => b'm',
+            Self::Unknown(byte) => byte,
+        }
+    }
+
+    /// Parse the server environment, warning about unknown ones in lenient mode
+    fn parse(byte: u8, parser: &mut Parser) -> Self {
+        let environment = Self::from_u8(byte);
+        if let Self::Unknown(byte) = environment {
+            parser.warn(Warning::UnknownServerEnvironment(byte));
+        }
+        environment
+    }
+
+    /// Byte of the server environment in a GoldSource response, in uppercase
+    fn to_goldsrc_u8(self) -> u8 {
+        match self {
+            Self::Unknown(byte) => byte,
+            environment => environment.to_u8().to_ascii_uppercase(),
+        }
+    }
+
+    /// Parse the server environment of a GoldSource response, sent in uppercase
+    fn parse_goldsrc(byte: u8, parser: &mut Parser) -> Self {
+        match Self::parse(byte.to_ascii_lowercase(), parser) {
+            Self::Unknown(_) => Self::Unknown(byte),
+            environment => environment,
         }
     }
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TheShipInfo {
     /// Indicates the game mode
     pub mode: TheShipMode,
@@ -132,7 +227,31 @@ pub struct TheShipInfo {
     pub duration: u8,
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+impl TheShipInfo {
+    /// Parse the additional info, left out if the game mode is unknown in lenient mode
+    fn from_bytes(
+        payload: &mut Cursor<Vec<u8>>,
+        parser: &mut Parser,
+    ) -> Result<Option<Self>, Error> {
+        let mode = payload.read_u8()?;
+        let witnesses = payload.read_u8()?;
+        let duration = payload.read_u8()?;
+
+        let warning = || Warning::UnknownTheShipMode(mode);
+        let mode = parser.recover(TheShipMode::from_u8(mode).map(Some), warning, None)?;
+        Ok(mode.map(|mode| TheShipInfo {
+            mode,
+            witnesses,
+            duration,
+        }))
+    }
+
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&[self.mode.to_u8(), self.witnesses, self.duration]);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TheShipMode {
     Hunt,
     Elimination,
@@ -154,23 +273,110 @@ impl TheShipMode {
             _ => Err(Error::InvalidResponse),
         }
     }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Hunt => 0,
+            Self::Elimination => 1,
+            Self::Duel => 2,
+            Self::Deathmatch => 3,
+            Self::VIPTeam => 4,
+            Self::TeamElimination => 5,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SourceTV {
     /// Spectator port number for SourceTV
     #[serde(rename = "sourcetv_port")]
-    pub port: i16,
+    pub port: u16,
 
     /// Name of the spectator server for SourceTV
     #[serde(rename = "sourcetv_name")]
     pub name: String,
 }
 
+impl SourceTV {
+    fn from_bytes(payload: &mut Cursor<Vec<u8>>) -> Result<Self, Error> {
+        Ok(SourceTV {
+            port: payload.read_u16::<LittleEndian>()?,
+            name: payload.read_string()?,
+        })
+    }
+
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.port.to_le_bytes());
+        buf.write_string(&self.name);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GoldSrcInfo {
+    /// IP address and port of the server
+    pub address: String,
+
+    /// Information about the mod, if the server runs one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mod_info: Option<ModInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModInfo {
+    /// URL to mod website
+    pub link: String,
+
+    /// URL to download the mod
+    pub download_link: String,
+
+    /// Version of mod installed on server
+    pub version: i32,
+
+    /// Space (in bytes) the mod takes up
+    pub size: i32,
+
+    /// Indicates whether the mod is multiplayer only
+    pub multiplayer_only: bool,
+
+    /// Indicates whether the mod uses its own DLL instead of the Half-Life one
+    pub own_dll: bool,
+}
+
+impl ModInfo {
+    fn from_bytes(payload: &mut Cursor<Vec<u8>>) -> Result<Self, Error> {
+        let link = payload.read_string()?;
+        let download_link = payload.read_string()?;
+        payload.read_u8()?;
+        Ok(ModInfo {
+            link,
+            download_link,
+            version: payload.read_i32::<LittleEndian>()?,
+            size: payload.read_i32::<LittleEndian>()?,
+            multiplayer_only: payload.read_u8()? != 0,
+            own_dll: payload.read_u8()? != 0,
+        })
+    }
+
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.write_string(&self.link);
+        buf.write_string(&self.download_link);
+        buf.push(0);
+        buf.extend_from_slice(&self.version.to_le_bytes());
+        buf.extend_from_slice(&self.size.to_le_bytes());
+        buf.push(self.multiplayer_only as u8);
+        buf.push(self.own_dll as u8);
+    }
+}
+
 impl Info {
-    fn from_bytes(mut payload: Cursor<Vec<u8>>) -> Result<Self, Error> {
+    pub(crate) fn from_bytes(
+        mut payload: Cursor<Vec<u8>>,
+        parser: &mut Parser,
+    ) -> Result<Self, Error> {
         let header = payload.read_u8()?;
-        if header != INFO_RESPONSE_HEADER {
+        if header == GOLDSRC_INFO_RESPONSE_HEADER {
+            return Self::from_goldsrc_bytes(payload, parser);
+        } else if header != INFO_RESPONSE_HEADER {
             return Err(Error::InvalidHeader {
                 expected: INFO_RESPONSE_HEADER,
                 found: header,
@@ -182,23 +388,30 @@ impl Info {
         let map = payload.read_string()?;
         let folder = payload.read_string()?;
         let game = payload.read_string()?;
-        let app_id = payload.read_i16::<LittleEndian>()?;
-        let players = payload.read_u8()?;
-        let max_players = payload.read_u8()?;
-        let bots = payload.read_u8()?;
-        let server_type = ServerType::from_u8(payload.read_u8()?)?;
-        let environment = ServerEnvironment::from_u8(payload.read_u8()?)?;
-        let password = payload.read_u8()? != 0;
-        let vac = payload.read_u8()? != 0;
+        let short_app_id = parser.field("app_id", payload.read_u16::<LittleEndian>())?;
+        let app_id = u32::from(short_app_id);
+        let players = parser.field("players", payload.read_u8())?;
+        let max_players = parser.field("max_players", payload.read_u8())?;
+        let bots = parser.field("bots", payload.read_u8())?;
+        let server_type = match parser.field("server_type", payload.read_u8().map(Some))? {
+            Some(byte) => ServerType::parse(byte, parser),
+            None => ServerType::Unknown(0),
+        };
+        let environment = match parser.field("environment", payload.read_u8().map(Some))? {
+            Some(byte) => ServerEnvironment::parse(byte, parser),
+            None => ServerEnvironment::Unknown(0),
+        };
+        let password = parser.field("password", payload.read_u8())? != 0;
+        let vac = parser.field("vac", payload.read_u8())? != 0;
         let the_ship = if app_id == THE_SHIP_APP_ID {
-            Some(TheShipInfo {
-                mode: TheShipMode::from_u8(payload.read_u8()?)?,
-                witnesses: payload.read_u8()?,
-                duration: payload.read_u8()?,
-            })
+            let the_ship = TheShipInfo::from_bytes(&mut payload, parser);
+            parser.field("the_ship", the_ship)?
         } else {
             None
         };
+        if is_exhausted(&payload) {
+            parser.warn(Warning::Truncated { field: "version" });
+        }
         let version = payload.read_string()?;
         let edf = if (payload.position() as usize) < payload.get_ref().len() {
             payload.read_u8()?
@@ -206,35 +419,39 @@ impl Info {
             0
         };
 
-        let port = if edf & 0x80 != 0 {
-            Some(payload.read_i16::<LittleEndian>()?)
+        let port = if edf & EDF_PORT != 0 {
+            parser.field("port", payload.read_u16::<LittleEndian>().map(Some))?
         } else {
             None
         };
 
-        let steam_id = if edf & 0x10 != 0 {
-            Some(payload.read_u64::<LittleEndian>()?)
+        let steam_id = if edf & EDF_STEAM_ID != 0 {
+            let steam_id = payload
+                .read_u64::<LittleEndian>()
+                .map(|id| Some(SteamId::from(id)));
+            parser.field("steam_id", steam_id)?
         } else {
             None
         };
 
-        let sourcetv_info = if edf & 0x40 != 0 {
-            Some(SourceTV {
-                port: payload.read_i16::<LittleEndian>()?,
-                name: payload.read_string()?,
-            })
+        let sourcetv_info = if edf & EDF_SOURCETV != 0 {
+            let sourcetv_info = SourceTV::from_bytes(&mut payload).map(Some);
+            parser.field("sourcetv_info", sourcetv_info)?
         } else {
             None
         };
 
-        let keywords = if edf & 0x20 != 0 {
+        let keywords = if edf & EDF_KEYWORDS != 0 {
             Some(payload.read_string()?)
         } else {
             None
         };
 
-        let game_id = if edf & 0x01 != 0 {
-            Some(payload.read_u64::<LittleEndian>()?)
+        let game_id = if edf & EDF_GAME_ID != 0 {
+            let game_id = payload
+                .read_u64::<LittleEndian>()
+                .map(|id| Some(GameId::from(id)));
+            parser.field("game_id", game_id)?
         } else {
             None
         };
@@ -245,7 +462,7 @@ impl Info {
             map,
             folder,
             game,
-            app_id,
+            app_id: full_app_id(short_app_id, game_id),
             players,
             max_players,
             bots,
@@ -261,38 +478,252 @@ impl Info {
             sourcetv_info,
             keywords,
             game_id,
+            goldsrc: None,
         })
     }
 
+    /// Parse the obsolete GoldSource response, whose header has already been read
+    fn from_goldsrc_bytes(
+        mut payload: Cursor<Vec<u8>>,
+        parser: &mut Parser,
+    ) -> Result<Self, Error> {
+        let address = payload.read_string()?;
+        let name = payload.read_string()?;
+        let map = payload.read_string()?;
+        let folder = payload.read_string()?;
+        let game = payload.read_string()?;
+        let players = parser.field("players", payload.read_u8())?;
+        let max_players = parser.field("max_players", payload.read_u8())?;
+        let protocol = parser.field("protocol", payload.read_u8())?;
+        let server_type = match parser.field("server_type", payload.read_u8().map(Some))? {
+            Some(byte) => ServerType::parse_goldsrc(byte, parser),
+            None => ServerType::Unknown(0),
+        };
+        let environment = match parser.field("environment", payload.read_u8().map(Some))? {
+            Some(byte) => ServerEnvironment::parse_goldsrc(byte, parser),
+            None => ServerEnvironment::Unknown(0),
+        };
+        let password = parser.field("password", payload.read_u8())? != 0;
+        let mod_info = if parser.field("mod", payload.read_u8())? != 0 {
+            let mod_info = ModInfo::from_bytes(&mut payload).map(Some);
+            parser.field("mod_info", mod_info)?
+        } else {
+            None
+        };
+        let vac = parser.field("vac", payload.read_u8())? != 0;
+        let bots = parser.field("bots", payload.read_u8())?;
+
+        Ok(Info {
+            protocol,
+            name,
+            map,
+            folder,
+            game,
+            app_id: 0,
+            players,
+            max_players,
+            bots,
+            server_type,
+            environment,
+            password,
+            vac,
+            the_ship: None,
+            version: String::new(),
+            edf: 0,
+            port: None,
+            steam_id: None,
+            sourcetv_info: None,
+            keywords: None,
+            game_id: None,
+            goldsrc: Some(GoldSrcInfo { address, mod_info }),
+        })
+    }
+
+    /// Decode a single packet info response, such as one made by [`Info::encode`]
+    pub fn decode(datagram: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes(single_packet_payload(datagram)?, &mut Parser::new(false))
+    }
+
+    /// Decode a single packet info response, returning what could be decoded of a
+    /// malformed one along with the problems found
+    pub fn decode_lenient(datagram: &[u8]) -> Result<(Self, Vec<Warning>), Error> {
+        let mut parser = Parser::new(true);
+        let info = Self::from_bytes(single_packet_payload(datagram)?, &mut parser)?;
+        Ok((info, parser.into_warnings()))
+    }
+
+    /// Encode the info as a single packet response, in the GoldSource format if it
+    /// has GoldSource info. The extra data flags are set from the optional fields
+    /// present, and fields the format lacks are left out.
+    ///
+    /// [`Info::decode`] reads the response back unchanged, except for the fields
+    /// the format rewrites or lacks:
+    /// - The known flags of `edf` are set from the optional fields present, so
+    ///   `edf: 0` with `port: Some` is decoded as `0x80`.
+    /// - The Ship info is always sent by The Ship servers, so `the_ship: None` is
+    ///   decoded as the `Hunt` mode with no witnesses and no duration.
+    /// - An app ID wider than 16 bits is only sent in the GameID, so `game_id: None`
+    ///   is decoded as the GameID of the app, with its extra data flag set.
+    /// - The GoldSource format has no `app_id`, `the_ship`, `version`, `edf` nor
+    ///   extra data fields, which are decoded as zero, empty or `None`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = HEADER_SINGLE_PACKET.to_le_bytes().to_vec();
+        if let Some(goldsrc) = &self.goldsrc {
+            self.write_goldsrc_bytes(goldsrc, &mut buf);
+            return buf;
+        }
+
+        buf.push(INFO_RESPONSE_HEADER);
+        buf.push(self.protocol);
+        buf.write_string(&self.name);
+        buf.write_string(&self.map);
+        buf.write_string(&self.folder);
+        buf.write_string(&self.game);
+        buf.extend_from_slice(&(self.app_id as u16).to_le_bytes());
+        buf.extend_from_slice(&[self.players, self.max_players, self.bots]);
+        buf.push(self.server_type.to_u8());
+        buf.push(self.environment.to_u8());
+        buf.push(self.password as u8);
+        buf.push(self.vac as u8);
+        if self.is_the_ship() {
+            match &self.the_ship {
+                Some(the_ship) => the_ship.write_bytes(&mut buf),
+                None => buf.extend_from_slice(&[0, 0, 0]),
+            }
+        }
+        buf.write_string(&self.version);
+
+        // An app ID too wide for its field is only conveyed by the GameID
+        let game_id = self
+            .game_id
+            .or_else(|| (self.app_id > u16::MAX as u32).then(|| GameId::from(self.app_id as u64)));
+        let flags = [
+            (EDF_PORT, self.port.is_some()),
+            (EDF_STEAM_ID, self.steam_id.is_some()),
+            (EDF_SOURCETV, self.sourcetv_info.is_some()),
+            (EDF_KEYWORDS, self.keywords.is_some()),
+            (EDF_GAME_ID, game_id.is_some()),
+        ];
+        let edf = flags.iter().fold(
+            self.edf,
+            |edf, &(flag, present)| {
+                if present { edf | flag } else { edf & !flag }
+            },
+        );
+        buf.push(edf);
+
+        if let Some(port) = self.port {
+            buf.extend_from_slice(&port.to_le_bytes());
+        }
+        if let Some(steam_id) = self.steam_id {
+            buf.extend_from_slice(&steam_id.as_u64().to_le_bytes());
+        }
+        if let Some(sourcetv_info) = &self.sourcetv_info {
+            sourcetv_info.write_bytes(&mut buf);
+        }
+        if let Some(keywords) = &self.keywords {
+            buf.write_string(keywords);
+        }
+        if let Some(game_id) = game_id {
+            buf.extend_from_slice(&game_id.as_u64().to_le_bytes());
+        }
+        buf
+    }
+
+    fn write_goldsrc_bytes(&self, goldsrc: &GoldSrcInfo, buf: &mut Vec<u8>) {
+        buf.push(GOLDSRC_INFO_RESPONSE_HEADER);
+        buf.write_string(&goldsrc.address);
+        buf.write_string(&self.name);
+        buf.write_string(&self.map);
+        buf.write_string(&self.folder);
+        buf.write_string(&self.game);
+        buf.extend_from_slice(&[self.players, self.max_players, self.protocol]);
+        buf.push(self.server_type.to_goldsrc_u8());
+        buf.push(self.environment.to_goldsrc_u8());
+        buf.push(self.password as u8);
+        match &goldsrc.mod_info {
+            Some(mod_info) => {
+                buf.push(1);
+                mod_info.write_bytes(buf);
+            }
+            None => buf.push(0),
+        }
+        buf.push(self.vac as u8);
+        buf.push(self.bots);
+    }
+
     pub fn is_the_ship(&self) -> bool {
-        self.app_id == THE_SHIP_APP_ID
+        Self::is_the_ship_app_id(self.app_id)
+    }
+
+    pub(crate) fn is_the_ship_app_id(app_id: u32) -> bool {
+        app_id == THE_SHIP_APP_ID
     }
 }
 
-impl<A: ToSocketAddrs> A2S<A> {
+/// App ID of the GameID, when its low 16 bits are the ones sent in the app ID field
+fn full_app_id(short_app_id: u16, game_id: Option<GameId>) -> u32 {
+    let game_app_id = game_id.map_or(0, GameId::app_id);
+    if game_app_id as u16 == short_app_id {
+        game_app_id
+    } else {
+        u32::from(short_app_id)
+    }
+}
+
+pub(crate) fn info_request(challenge: Option<i32>) -> Vec<u8> {
+    let mut request = Vec::with_capacity(29);
+    request.extend_from_slice(&HEADER_SINGLE_PACKET.to_le_bytes());
+    request.push(INFO_REQUEST_HEADER);
+    request.extend_from_slice(INFO_REQUEST_PAYLOAD.as_bytes());
+    if let Some(challenge) = challenge {
+        request.extend_from_slice(&challenge.to_le_bytes());
+    }
+    request
+}
+
+impl<A: ToSocketAddrs, T: Transport> A2S<A, T> {
     pub fn info(&self) -> Result<Info, Error> {
-        let mut request = Vec::with_capacity(29);
-        request.extend_from_slice(&HEADER_SINGLE_PACKET.to_le_bytes());
-        request.push(INFO_REQUEST_HEADER);
-        request.extend_from_slice(INFO_REQUEST_PAYLOAD.as_bytes());
-
-        let mut data = self.send_and_recv(&request)?;
-
-        let resp_header = data.read_u8()?;
-
-        if resp_header == INFO_RESPONSE_HEADER {
-            data.set_position(data.position() - 1);
-            return Info::from_bytes(data);
-        } else if resp_header == S2C_CHALLENGE {
-            let mut challenge = [0u8; 4];
-            data.read_exact(&mut challenge)?;
-            request.extend_from_slice(&challenge);
-            Info::from_bytes(self.send_and_recv(&request)?)
-        } else {
-            return Err(Error::InvalidHeader {
-                expected: S2C_CHALLENGE,
-                found: resp_header,
-            });
-        }
+        self.info_outcome().map(|outcome| outcome.value)
+    }
+
+    /// Query the info, along with measurements of the exchange
+    pub fn info_outcome(&self) -> Result<QueryOutcome<Info>, Error> {
+        self.info_until(self.config.deadline())
+    }
+
+    pub(crate) fn info_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Info>, Error> {
+        let query = self.config.query(QueryKind::Info, self.app_id());
+        Ok(self.execute(query, deadline)?.map(into_info))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<A: tokio::net::ToSocketAddrs> AsyncA2S<A> {
+    pub async fn info(&self) -> Result<Info, Error> {
+        self.info_outcome().await.map(|outcome| outcome.value)
+    }
+
+    /// Query the info, along with measurements of the exchange
+    pub async fn info_outcome(&self) -> Result<QueryOutcome<Info>, Error> {
+        self.info_until(self.config.deadline()).await
+    }
+
+    pub(crate) async fn info_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Info>, Error> {
+        let query = self.config.query(QueryKind::Info, self.app_id());
+        Ok(self.execute(query, deadline).await?.map(into_info))
+    }
+}
+
+fn into_info(response: Response) -> Info {
+    match response {
+        Response::Info(info) => *info,
+        _ => unreachable!("info query answered with info"),
     }
 }
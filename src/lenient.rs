@@ -0,0 +1,99 @@
+use std::io::Cursor;
+
+use crate::errors::Error;
+
+/// Problem found in a malformed response, which lenient parsing worked around
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The response ended before this field, which was left to its default value
+    Truncated { field: &'static str },
+
+    /// The response holds a different number of players or rules than it announced
+    CountMismatch { expected: usize, found: usize },
+
+    /// Unknown server type byte, kept as [`ServerType::Unknown`](crate::info::ServerType::Unknown)
+    UnknownServerType(u8),
+
+    /// Unknown server environment byte, kept as
+    /// [`ServerEnvironment::Unknown`](crate::info::ServerEnvironment::Unknown)
+    UnknownServerEnvironment(u8),
+
+    /// Unknown game mode of The Ship, whose additional info was left out
+    UnknownTheShipMode(u8),
+}
+
+/// How malformed responses are handled while parsing them
+#[derive(Debug, Default)]
+pub(crate) struct Parser {
+    lenient: bool,
+    warnings: Vec<Warning>,
+}
+
+impl Parser {
+    pub(crate) fn new(lenient: bool) -> Self {
+        Parser {
+            lenient,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Use the fallback value and record the warning if the field could not be
+    /// parsed in lenient mode, or fail in strict mode
+    pub(crate) fn recover<T, E: Into<Error>>(
+        &mut self,
+        res: Result<T, E>,
+        warning: impl FnOnce() -> Warning,
+        fallback: T,
+    ) -> Result<T, Error> {
+        match res {
+            Err(_) if self.lenient => {
+                self.warn(warning());
+                Ok(fallback)
+            }
+            res => res.map_err(Into::into),
+        }
+    }
+
+    /// Read an optional trailing field, falling back to its default value if the
+    /// response ended before it in lenient mode
+    pub(crate) fn field<T: Default, E: Into<Error>>(
+        &mut self,
+        field: &'static str,
+        res: Result<T, E>,
+    ) -> Result<T, Error> {
+        self.recover(res, || Warning::Truncated { field }, T::default())
+    }
+
+    /// Record a warning in lenient mode, only once for truncations since every
+    /// following field is missing too
+    pub(crate) fn warn(&mut self, warning: Warning) {
+        if !self.lenient {
+            return;
+        }
+        let already_truncated = matches!(warning, Warning::Truncated { .. })
+            && self
+                .warnings
+                .iter()
+                .any(|w| matches!(w, Warning::Truncated { .. }));
+        if !already_truncated {
+            self.warnings.push(warning);
+        }
+    }
+
+    pub(crate) fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+}
+
+/// Whether the whole payload has been read
+pub(crate) fn is_exhausted(payload: &Cursor<Vec<u8>>) -> bool {
+    payload.position() as usize >= payload.get_ref().len()
+}
@@ -1,131 +1,646 @@
+mod builder;
 pub mod errors;
+pub mod fleet;
 pub mod info;
+pub mod lenient;
+pub mod ping;
 pub mod players;
+pub mod protocol;
 pub mod rules;
+pub mod server;
+pub mod snapshot;
+pub mod steam;
+pub mod transport;
 mod utils;
 
-use std::net::ToSocketAddrs;
-use std::net::UdpSocket;
+#[cfg(feature = "tokio")]
+mod async_client;
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncA2S;
+pub use builder::A2SBuilder;
+pub use transport::{ScriptedTransport, Transport};
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use builder::Config;
+use byteorder::LittleEndian;
+use bzip2::read::BzDecoder;
 use errors::Error;
+use ping::PingMethod;
+use protocol::{Output, Query, QueryOutcome, Response, Stage};
+use serde::{Deserialize, Serialize};
 use std::io::{Cursor, Read};
+use utils::read_bytes::ReadBytes;
+
+/// Version of the JSON representation of [`Info`](info::Info), [`Player`](players::Player)
+/// and [`Rule`](rules::Rule), bumped whenever a release changes it incompatibly
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Data tagged with the version of its JSON representation, as written by the
+/// `a2s-query` binary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    /// Version of the representation of `data`
+    pub schema_version: u32,
+
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    /// Tag the data with the current [`JSON_SCHEMA_VERSION`]
+    pub fn new(data: T) -> Self {
+        Versioned {
+            schema_version: JSON_SCHEMA_VERSION,
+            data,
+        }
+    }
+}
 
 const HEADER_SINGLE_PACKET: i32 = -1;
 const HEADER_MULTI_PACKET: i32 = -2;
 const S2C_CHALLENGE: u8 = 0x41;
+const COMPRESSED_ANSWER_ID_FLAG: u32 = 0x8000_0000;
 
-pub struct A2S<A: ToSocketAddrs> {
+/// Largest UDP payload, so that no datagram gets truncated
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+/// Largest number of packets a split response may be made of
+const MAX_PACKETS: u8 = 64;
+/// Largest size a compressed response may announce, so that a server cannot
+/// make the client inflate an unbounded payload
+const MAX_DECOMPRESSED_SIZE: u32 = 1 << 20;
+
+/// Engine family of the queried server, which determines the split packet format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// Source engine and later, with separate total and number bytes and a size field
+    #[default]
+    Source,
+
+    /// GoldSource engine (Half-Life 1 based games), packing total and number in one byte
+    GoldSource,
+}
+
+/// App IDs of Source games whose split packet headers lack the maximum packet size field
+const NO_SPLIT_SIZE_APP_IDS: &[u32] = &[215, 17550, 17700];
+
+/// Deviations of some servers from the protocol, adjusting how their responses are parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// Whether Source split packet headers carry the maximum packet size field,
+    /// which games of the 2006 engine and older omit
+    pub split_size_field: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            split_size_field: true,
+        }
+    }
+}
+
+impl Quirks {
+    /// Quirks of the game with the given app ID
+    pub fn for_app_id(app_id: u32) -> Self {
+        Quirks {
+            split_size_field: !NO_SPLIT_SIZE_APP_IDS.contains(&app_id),
+        }
+    }
+
+    /// Quirks set explicitly, or else those of the app ID if known
+    fn resolve(quirks: Option<Quirks>, app_id: Option<u32>) -> Self {
+        match (quirks, app_id) {
+            (Some(quirks), _) => quirks,
+            (None, Some(app_id)) => Quirks::for_app_id(app_id),
+            (None, None) => Quirks::default(),
+        }
+    }
+}
+
+pub struct A2S<A: ToSocketAddrs, T = UdpSocket> {
     address: A,
+    config: Config,
+    /// Transport to the server, created on first use and dropped on I/O errors
+    transport: Mutex<Option<T>>,
+    /// App ID of the server, cached from the last info response
+    app_id: Mutex<Option<u32>>,
+    /// Address of the server last connected to, kept for the context of errors
+    last_peer: Mutex<Option<SocketAddr>>,
+    /// Ping exchange the server answers, once detected
+    ping_method: Mutex<Option<PingMethod>>,
 }
 
-struct Packet {
-    number: u8,
-    payload: Vec<u8>,
+/// Size and checksum of a bzip2 compressed response, sent in its first packet
+struct Compression {
+    decompressed_size: u32,
+    crc32: u32,
 }
 
-impl<A: ToSocketAddrs> A2S<A> {
-    pub fn new(address: A) -> Self {
-        A2S { address }
+/// Consumes the datagrams answering a request until a result is available
+trait Collector {
+    type Output;
+
+    /// Forget the datagrams of a previous answer, before sending a request again
+    fn reset(&mut self);
+
+    /// Requests to send at each attempt: the given one, unless the collector
+    /// answers several requests at once
+    fn requests<'a>(&'a self, request: &'a [u8]) -> Vec<&'a [u8]> {
+        vec![request]
     }
 
-    fn send_and_recv_with_challenge(&self, req_header: u8) -> Result<Cursor<Vec<u8>>, Error> {
-        let data = Vec::with_capacity(9);
-        let mut data = Cursor::new(data);
+    /// Feed a received datagram, returning the result once it is available
+    fn push(&mut self, datagram: &[u8]) -> Result<Option<Self::Output>, Error>;
 
-        data.write_i32::<LittleEndian>(HEADER_SINGLE_PACKET)?;
-        data.write_u8(req_header)?;
-        data.write_i32::<LittleEndian>(HEADER_SINGLE_PACKET)?;
+    /// Error for the first packet missing from a split response being reassembled
+    fn missing_fragment(&self) -> Option<Error>;
+}
 
-        let mut res = self.send_and_recv(data.get_ref())?;
+/// Collects the datagrams of a response until the full payload is available
+struct PacketAssembler {
+    engine: Engine,
+    quirks: Quirks,
+    answer_id: Option<i32>,
+    /// Payload of each packet of the response, by packet number
+    packets: Vec<Option<Vec<u8>>>,
+    received: usize,
+    compression: Option<Compression>,
+}
 
-        let resp_header = res.read_u8()?;
-        if resp_header != S2C_CHALLENGE {
-            res.set_position(res.position() - 1);
-            return Ok(res);
+impl PacketAssembler {
+    fn new(engine: Engine, quirks: Quirks) -> Self {
+        PacketAssembler {
+            engine,
+            quirks,
+            answer_id: None,
+            packets: Vec::new(),
+            received: 0,
+            compression: None,
         }
-        let challenge = res.read_i32::<LittleEndian>()?;
+    }
 
-        data.set_position(5);
-        data.write_i32::<LittleEndian>(challenge)?;
-        self.send_and_recv(&data.get_ref())
+    /// Length of the header of a split packet, up to its payload
+    fn split_header_len(&self) -> usize {
+        match self.engine {
+            Engine::Source if self.quirks.split_size_field => 12,
+            Engine::Source => 10,
+            Engine::GoldSource => 9,
+        }
     }
 
-    fn send_and_recv(&self, data: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        socket.connect(&self.address)?;
-        socket.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+    /// Feed a received datagram, returning the payload once the response is complete.
+    /// Duplicated packets, packets of another response and datagrams too short to be
+    /// a packet are ignored once a split response started.
+    fn push(&mut self, datagram: &[u8]) -> Result<Option<Cursor<Vec<u8>>>, Error> {
+        match self.answer_id {
+            None if datagram.len() < 5 => return Err(Error::InvalidResponse),
+            Some(_) if datagram.len() < self.split_header_len() => return Ok(None),
+            _ => {}
+        }
+
+        let mut data = Cursor::new(datagram);
+
+        let header = data.read_i32::<LittleEndian>()?;
+        match header {
+            HEADER_SINGLE_PACKET if self.answer_id.is_none() => {
+                let mut res = Cursor::new(datagram.to_vec());
+                res.set_position(4);
+                return Ok(Some(res));
+            }
+            HEADER_SINGLE_PACKET => return Ok(None),
+            HEADER_MULTI_PACKET => {}
+            _ => return Err(Error::InvalidResponse),
+        }
 
-        socket.send(&data)?;
+        let id = data.read_i32::<LittleEndian>()?;
+        if self.answer_id.is_some_and(|answer_id| answer_id != id) {
+            return Ok(None);
+        }
 
-        let mut buf = [0u8; 1260];
+        let (total_packets, packet_number, packet_size) = match self.engine {
+            Engine::Source if self.quirks.split_size_field => (
+                data.read_u8()?,
+                data.read_u8()?,
+                data.read_i16::<LittleEndian>()?,
+            ),
+            Engine::Source => (data.read_u8()?, data.read_u8()?, 0),
+            Engine::GoldSource => {
+                let packet_info = data.read_u8()?;
+                (packet_info & 0x0F, packet_info >> 4, 0)
+            }
+        };
 
-        let n_received = socket.recv(&mut buf)?;
-        if n_received < 5 {
+        if total_packets == 0 || packet_number >= total_packets {
             return Err(Error::InvalidResponse);
         }
+        if total_packets > MAX_PACKETS {
+            return Err(Error::TooManyPackets {
+                total: total_packets,
+                max: MAX_PACKETS,
+            });
+        }
+        if self.answer_id.is_none() {
+            self.answer_id = Some(id);
+            self.packets = vec![None; total_packets as usize];
+        } else if self.packets.len() != total_packets as usize {
+            return Err(Error::InvalidResponse);
+        }
+        if self.packets[packet_number as usize].is_some() {
+            return Ok(None);
+        }
+
+        let is_compressed =
+            self.engine == Engine::Source && id as u32 & COMPRESSED_ANSWER_ID_FLAG != 0;
+        if is_compressed && packet_number == 0 {
+            let decompressed_size = data.read_u32::<LittleEndian>()?;
+            if decompressed_size > MAX_DECOMPRESSED_SIZE {
+                return Err(Error::ResponseTooLarge {
+                    size: decompressed_size,
+                    max: MAX_DECOMPRESSED_SIZE,
+                });
+            }
+            self.compression = Some(Compression {
+                decompressed_size,
+                crc32: data.read_u32::<LittleEndian>()?,
+            });
+        }
 
-        let mut data = buf.to_vec();
-        data.truncate(n_received);
-        let mut data = Cursor::new(data);
+        let payload = &datagram[data.position() as usize..];
+        if packet_size > 0 && payload.len() > packet_size as usize {
+            return Err(Error::InvalidResponse);
+        }
+        self.packets[packet_number as usize] = Some(payload.to_vec());
+        self.received += 1;
 
-        let header = data.read_i32::<LittleEndian>()?;
-        if header == HEADER_SINGLE_PACKET {
-            Ok(data)
-        } else if header == HEADER_MULTI_PACKET {
-            let mut packets = Vec::new();
+        if self.received < self.packets.len() {
+            return Ok(None);
+        }
 
-            let answer_id = data.read_i32::<LittleEndian>()?;
+        let mut payload = Vec::new();
+        for packet in self.packets.drain(..).flatten() {
+            payload.extend(packet);
+        }
 
-            if answer_id >> 31 == 1 {
-                return Err(Error::NotImplemented {
-                    feature: "gzip2 is not handled",
-                });
+        if is_compressed {
+            payload = self.decompress(&payload)?;
+        }
+
+        let mut res = Cursor::new(payload);
+        res.read_i32::<LittleEndian>()?;
+        Ok(Some(res))
+    }
+
+    fn reset(&mut self) {
+        *self = PacketAssembler::new(self.engine, self.quirks);
+    }
+
+    fn missing_fragment(&self) -> Option<Error> {
+        self.answer_id?;
+        let number = self.packets.iter().position(Option::is_none)?;
+        Some(Error::FragmentMissing {
+            number: number as u8,
+            total: self.packets.len() as u8,
+        })
+    }
+
+    /// Decompress a reassembled bzip2 payload and verify its size and checksum.
+    /// Decompression stops as soon as the announced size is exceeded.
+    fn decompress(&self, compressed: &[u8]) -> Result<Vec<u8>, Error> {
+        let compression = self.compression.as_ref().ok_or(Error::InvalidResponse)?;
+        let decompressed_size = compression.decompressed_size.min(MAX_DECOMPRESSED_SIZE) as usize;
+
+        // One byte more than announced is enough to tell the payload is larger
+        let mut payload = Vec::with_capacity(decompressed_size);
+        BzDecoder::new(compressed)
+            .take(decompressed_size as u64 + 1)
+            .read_to_end(&mut payload)?;
+
+        if payload.len() != compression.decompressed_size as usize
+            || crc32fast::hash(&payload) != compression.crc32
+        {
+            return Err(Error::ChecksumMismatch);
+        }
+
+        Ok(payload)
+    }
+}
+
+impl Collector for PacketAssembler {
+    type Output = Cursor<Vec<u8>>;
+
+    fn reset(&mut self) {
+        PacketAssembler::reset(self);
+    }
+
+    fn push(&mut self, datagram: &[u8]) -> Result<Option<Cursor<Vec<u8>>>, Error> {
+        PacketAssembler::push(self, datagram)
+    }
+
+    fn missing_fragment(&self) -> Option<Error> {
+        PacketAssembler::missing_fragment(self)
+    }
+}
+
+/// Error for a datagram which could not be received, telling which packet is
+/// missing if the server stopped in the middle of a split response
+fn recv_error<C: Collector>(collector: &C, e: std::io::Error) -> Error {
+    match Error::from(e) {
+        Error::Timeout => collector.missing_fragment().unwrap_or(Error::Timeout),
+        e => e,
+    }
+}
+
+/// Open a transport on the configured local address and connect it to the server
+fn connect<T: Transport>(address: SocketAddr, config: &Config) -> Result<T, Error> {
+    let mut last_error = None;
+
+    for local in config.local_addrs(address)? {
+        match T::connect(local, address) {
+            Ok(transport) => return Ok(transport),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => last_error = Some(e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(last_error
+        .expect("at least one local address is tried")
+        .into())
+}
+
+/// Build a request expecting a challenge, filled with the given challenge number
+fn challenge_request(req_header: u8, challenge: i32) -> Vec<u8> {
+    let mut data = Vec::with_capacity(9);
+
+    data.extend_from_slice(&HEADER_SINGLE_PACKET.to_le_bytes());
+    data.push(req_header);
+    data.extend_from_slice(&challenge.to_le_bytes());
+
+    data
+}
+
+/// Payload of a single packet response, positioned after its header
+fn single_packet_payload(datagram: &[u8]) -> Result<Cursor<Vec<u8>>, Error> {
+    let mut payload = Cursor::new(datagram.to_vec());
+    if payload.read_i32::<LittleEndian>()? != HEADER_SINGLE_PACKET {
+        return Err(Error::InvalidResponse);
+    }
+    Ok(payload)
+}
+
+/// Read the challenge number if the response is a challenge, rewinding it otherwise
+fn read_challenge(res: &mut Cursor<Vec<u8>>) -> Result<Option<i32>, Error> {
+    let resp_header = res.read_u8()?;
+    if resp_header != S2C_CHALLENGE {
+        res.set_position(res.position() - 1);
+        return Ok(None);
+    }
+    Ok(Some(res.read_i32::<LittleEndian>()?))
+}
+
+impl<A: ToSocketAddrs> A2S<A> {
+    pub fn new(address: A) -> Self {
+        A2S {
+            address,
+            config: Config::default(),
+            transport: Mutex::new(None),
+            app_id: Mutex::new(None),
+            last_peer: Mutex::new(None),
+            ping_method: Mutex::new(None),
+        }
+    }
+
+    /// Create a builder to configure timeouts and retries
+    pub fn builder(address: A) -> A2SBuilder<A> {
+        A2SBuilder::new(address)
+    }
+
+    /// Send queries from the given socket, connecting it to the server. If the
+    /// socket has to be recreated after an error, its local address is bound again.
+    pub fn with_socket(mut self, socket: UdpSocket) -> Result<Self, Error> {
+        let local = socket.local_addr()?;
+        let mut last_error = None;
+
+        for address in self.resolve()? {
+            if address.is_ipv4() != local.is_ipv4() {
+                continue;
             }
+            match socket.connect(address) {
+                Ok(()) => {
+                    self.config.bind_like(local);
+                    self.transport = Mutex::new(Some(socket));
+                    return Ok(self);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
 
-            data.set_position(data.position() - 4);
-
-            loop {
-                let id = data.read_i32::<LittleEndian>()?;
-                if id != answer_id {
-                    return Err(Error::UnexpectedAnswerID {
-                        expected: answer_id,
-                        found: id,
-                    });
-                };
-
-                let (total_packets, packet_number) = (data.read_u8()?, data.read_u8()?);
-
-                let payload_size = data.read_i16::<LittleEndian>()?;
-                let mut payload = Vec::with_capacity(payload_size as usize);
-                data.read_to_end(&mut payload)?;
-                packets.push(Packet {
-                    number: packet_number,
-                    payload: payload,
-                });
+        Err(last_error
+            .unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable))
+            .into())
+    }
+}
+
+impl<A: ToSocketAddrs, T: Transport> A2S<A, T> {
+    /// Set the engine of the server, used to reassemble split responses
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.config.engine = engine;
+        self
+    }
+
+    /// Set the quirks of the server, instead of looking them up by its app ID
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.config.quirks = Some(quirks);
+        self
+    }
+
+    /// Return what could be parsed of malformed responses instead of failing,
+    /// recording the problems as [`QueryOutcome::warnings`]
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.config.lenient = lenient;
+        self
+    }
+
+    /// Exchange datagrams with the server through the given transport instead of
+    /// a UDP socket
+    pub fn with_transport<U: Transport>(self, transport: U) -> A2S<A, U> {
+        A2S {
+            address: self.address,
+            config: self.config,
+            transport: Mutex::new(Some(transport)),
+            app_id: self.app_id,
+            last_peer: self.last_peer,
+            ping_method: self.ping_method,
+        }
+    }
+
+    /// App ID of the server, known once its info has been fetched
+    pub fn app_id(&self) -> Option<u32> {
+        *self.app_id.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run the query to completion, caching the app ID of the server once known
+    fn execute(
+        &self,
+        mut query: Query,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Response>, Error> {
+        let mut request = query.request().to_vec();
+
+        let res = loop {
+            match self.send_and_recv(&mut query, &request, deadline) {
+                Ok(Output::Send(next)) => request = next,
+                Ok(Output::Done(response)) => break Ok(query.outcome(response, self.last_peer())),
+                Err(e) => break Err(e),
+            }
+        };
 
-                if packets.len() == total_packets as usize {
-                    break;
+        if let Some(app_id) = query.app_id() {
+            *self.app_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(app_id);
+        }
+        res.map_err(|e| e.with_context(Some(query.kind()), query.stage(), self.last_peer()))
+    }
+
+    /// Send the request, sending it again if the server does not answer in time
+    fn send_and_recv<C: Collector>(
+        &self,
+        collector: &mut C,
+        data: &[u8],
+        deadline: Option<Instant>,
+    ) -> Result<C::Output, Error> {
+        let mut attempt = 0;
+        loop {
+            let timeout = self.config.attempt_timeout(deadline)?;
+            match self.send_and_recv_once(collector, data, timeout) {
+                Err(e) if e.is_timeout() && attempt < self.config.retries => {
+                    attempt += 1;
+                    thread::sleep(self.config.backoff_delay(attempt, deadline));
                 }
+                res => return res,
+            }
+        }
+    }
+
+    fn send_and_recv_once<C: Collector>(
+        &self,
+        collector: &mut C,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<C::Output, Error> {
+        let mut transport = self
+            .transport
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
 
-                let n = socket.recv(&mut buf)?;
-                let mut a: Vec<u8> = buf.to_vec();
-                a.truncate(n);
-                data = Cursor::new(a);
-                data.read_i32::<LittleEndian>()?;
+        let res = match transport.as_mut() {
+            Some(connected) => match connected.discard_pending() {
+                Ok(()) => self.exchange(connected, collector, data, timeout),
+                Err(e) => Err(e.into()),
+            },
+            None => self.connect_and_exchange(&mut transport, collector, data, timeout),
+        };
+        if matches!(&res, Err(e @ Error::Io(_)) if !e.is_timeout()) {
+            *transport = None;
+        }
+        res
+    }
+
+    /// Try each resolved address of the server in turn, sharing the timeout between
+    /// them, and keep the transport of the first one answering
+    fn connect_and_exchange<C: Collector>(
+        &self,
+        transport: &mut Option<T>,
+        collector: &mut C,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<C::Output, Error> {
+        let addresses = self
+            .resolve()
+            .map_err(|e| e.with_context(None, Stage::Connect, None))?;
+        let timeout_at = Instant::now() + timeout;
+        let mut last_error = None;
+
+        for (i, address) in addresses.iter().enumerate() {
+            let remaining = timeout_at.saturating_duration_since(Instant::now());
+            let address_timeout = remaining / (addresses.len() - i) as u32;
+
+            let mut connected = match connect(*address, &self.config) {
+                Ok(connected) => {
+                    *self
+                        .last_peer
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner) = Some(*address);
+                    connected
+                }
+                Err(e) => {
+                    last_error = Some(e.with_context(None, Stage::Connect, Some(*address)));
+                    continue;
+                }
+            };
+            match self.exchange(&mut connected, collector, data, address_timeout) {
+                Err(e) if e.is_network() => last_error = Some(e),
+                res => {
+                    *transport = Some(connected);
+                    return res;
+                }
             }
+        }
 
-            packets.sort_by_key(|p| p.number);
+        Err(last_error.expect("at least one address is resolved"))
+    }
+
+    fn exchange<C: Collector>(
+        &self,
+        transport: &mut T,
+        collector: &mut C,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<C::Output, Error> {
+        let timeout_at = Instant::now() + timeout;
+        collector.reset();
+        for request in collector.requests(data) {
+            transport.send(request)?;
+        }
 
-            let mut payload = Vec::new();
-            for packet in packets {
-                payload.extend(packet.payload);
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+
+        loop {
+            let n_received = transport
+                .recv(&mut buf, timeout_at)
+                .map_err(|e| recv_error(collector, e))?;
+            if let Some(res) = collector.push(&buf[..n_received])? {
+                return Ok(res);
             }
-            let mut res = Cursor::new(payload);
-            res.read_i32::<LittleEndian>()?;
-            Ok(res)
-        } else {
-            return Err(Error::InvalidResponse);
         }
     }
+
+    /// Resolve every address of the server
+    fn resolve(&self) -> Result<Vec<SocketAddr>, Error> {
+        let addresses: Vec<SocketAddr> = self.address.to_socket_addrs()?.collect();
+        if addresses.is_empty() {
+            return Err(std::io::Error::from(std::io::ErrorKind::AddrNotAvailable).into());
+        }
+        Ok(addresses)
+    }
+
+    /// Address of the server which answered, once a query succeeded
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.transport
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .and_then(Transport::peer_addr)
+    }
+
+    /// Address of the server currently or last connected to, even if its
+    /// transport was dropped after an error
+    pub(crate) fn last_peer(&self) -> Option<SocketAddr> {
+        self.peer_addr().or(*self
+            .last_peer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner))
+    }
 }
@@ -1,8 +1,8 @@
-use anyhow;
 use clap::{Parser, Subcommand};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::time::Duration;
 
-use a2s_query::A2S;
+use a2s_query::{A2S, Versioned};
 
 #[derive(Parser)]
 #[command(version)]
@@ -15,6 +15,14 @@ struct Args {
     #[arg(long, default_value_t = 27015)]
     port: u16,
 
+    /// Time (in milliseconds) to wait for each answer of the server
+    #[arg(long, default_value_t = 5000)]
+    timeout: u64,
+
+    /// Number of times a request is sent again when the server does not answer
+    #[arg(long, default_value_t = 0)]
+    retries: u32,
+
     #[command(subcommand)]
     request: A2SRequest,
 }
@@ -26,30 +34,32 @@ enum A2SRequest {
     Rules,
 }
 
+fn to_json<T: Serialize>(data: T) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Versioned::new(data))
+}
+
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
     let Args {
         host,
         port,
+        timeout,
+        retries,
         request,
     } = args;
 
     let address = format!("{host}:{port}");
 
-    let a2s = A2S::new(address);
+    let a2s = A2S::builder(address)
+        .timeout(Duration::from_millis(timeout))
+        .retries(retries)
+        .build();
 
     let data_json = match request {
-        A2SRequest::Info => serde_json::to_string_pretty(&a2s.info()?)?,
-        A2SRequest::Players => serde_json::to_string_pretty(&a2s.players()?)?,
-        A2SRequest::Rules => {
-            let rules: HashMap<_, _> = a2s
-                .rules()?
-                .iter()
-                .map(|r| (r.name.to_string(), r.value.to_string()))
-                .collect();
-            serde_json::to_string_pretty(&rules)?
-        }
+        A2SRequest::Info => to_json(a2s.info()?)?,
+        A2SRequest::Players => to_json(a2s.players()?)?,
+        A2SRequest::Rules => to_json(a2s.rules()?)?,
     };
 
     println!("{}", data_json);
@@ -0,0 +1,161 @@
+use std::net::ToSocketAddrs;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use crate::A2S;
+use crate::errors::Error;
+use crate::protocol::{Ping, Stage};
+use crate::transport::Transport;
+
+// A2A_PING
+pub(crate) const PING_REQUEST_HEADER: u8 = 0x69;
+pub(crate) const PING_RESPONSE_HEADER: u8 = 0x6A;
+
+/// Time left to the server to answer A2A_PING while detecting the ping method,
+/// as most recent servers never answer it
+const A2A_PING_PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Exchange used to measure the round-trip time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PingMethod {
+    /// Deprecated A2A_PING request, not answered by most recent servers
+    A2APing,
+
+    /// Challenge request, answered by any server supporting A2S_PLAYER
+    Challenge,
+}
+
+/// Statistics over a series of pings
+#[derive(Debug, Clone, PartialEq)]
+pub struct PingStats {
+    /// Number of pings sent
+    pub sent: u32,
+
+    /// Number of pings answered
+    pub received: u32,
+
+    /// Shortest round-trip time
+    pub min: Duration,
+
+    /// Average round-trip time
+    pub avg: Duration,
+
+    /// Longest round-trip time
+    pub max: Duration,
+
+    /// Mean difference between consecutive round-trip times
+    pub jitter: Duration,
+}
+
+impl PingStats {
+    fn from_samples(sent: u32, samples: &[Duration]) -> Self {
+        let received = samples.len() as u32;
+        let jitter = samples
+            .windows(2)
+            .map(|w| w[0].abs_diff(w[1]))
+            .sum::<Duration>()
+            .checked_div(received.saturating_sub(1))
+            .unwrap_or_default();
+
+        PingStats {
+            sent,
+            received,
+            min: samples.iter().copied().min().unwrap_or_default(),
+            avg: samples
+                .iter()
+                .sum::<Duration>()
+                .checked_div(received)
+                .unwrap_or_default(),
+            max: samples.iter().copied().max().unwrap_or_default(),
+            jitter,
+        }
+    }
+
+    /// Ratio of pings left unanswered, between 0 and 1
+    pub fn loss(&self) -> f32 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        (self.sent - self.received) as f32 / self.sent as f32
+    }
+}
+
+impl<A: ToSocketAddrs, T: Transport> A2S<A, T> {
+    /// Measure the round-trip time to the server, using A2A_PING if it is
+    /// supported and timing a challenge request otherwise. The exchange answered
+    /// is remembered for the next pings.
+    pub fn ping(&self) -> Result<Duration, Error> {
+        if let Some(method) = self.ping_method() {
+            return self.ping_with(method);
+        }
+
+        let timeout = self
+            .config
+            .attempt_timeout(self.config.deadline())
+            .map_err(|e| e.with_context(None, Stage::Request, self.last_peer()))?;
+        let res = match self.ping_once(PingMethod::A2APing, timeout.min(A2A_PING_PROBE_TIMEOUT)) {
+            Ok(rtt) => Ok((rtt, PingMethod::A2APing)),
+            Err(e) if e.is_timeout() => self
+                .ping_with(PingMethod::Challenge)
+                .map(|rtt| (rtt, PingMethod::Challenge)),
+            Err(e) => Err(e),
+        };
+
+        let (rtt, method) = res?;
+        *self
+            .ping_method
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(method);
+        Ok(rtt)
+    }
+
+    /// Ping exchange the server answered, once detected by [`A2S::ping`]
+    pub fn ping_method(&self) -> Option<PingMethod> {
+        *self
+            .ping_method
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Measure the round-trip time with the given exchange. The request is sent
+    /// only once, a lost ping is never retried.
+    pub fn ping_with(&self, method: PingMethod) -> Result<Duration, Error> {
+        let timeout = self
+            .config
+            .attempt_timeout(self.config.deadline())
+            .map_err(|e| e.with_context(None, Stage::Request, self.last_peer()))?;
+        self.ping_once(method, timeout)
+    }
+
+    fn ping_once(&self, method: PingMethod, timeout: Duration) -> Result<Duration, Error> {
+        let mut ping = Ping::new(
+            method,
+            self.config.engine,
+            self.config.quirks(self.app_id()),
+        );
+        let request = ping.request().to_vec();
+
+        self.send_and_recv_once(&mut ping, &request, timeout)
+            .map_err(|e| e.with_context(None, ping.stage(), self.last_peer()))
+    }
+
+    /// Send `count` pings one after the other and compute statistics over them.
+    /// Fails only if none of them is answered.
+    pub fn ping_series(&self, count: u32) -> Result<PingStats, Error> {
+        let mut samples = Vec::with_capacity(count as usize);
+        let mut last_error = None;
+
+        for _ in 0..count {
+            match self.ping() {
+                Ok(rtt) => samples.push(rtt),
+                Err(e) if e.is_timeout() => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        match last_error {
+            Some(e) if samples.is_empty() => Err(e),
+            _ => Ok(PingStats::from_samples(count, &samples)),
+        }
+    }
+}
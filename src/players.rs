@@ -1,17 +1,27 @@
-use byteorder::{LittleEndian, ReadBytesExt};
-use serde::Serialize;
+use byteorder::LittleEndian;
+use serde::{Deserialize, Serialize};
 use std::io::Cursor;
 use std::net::ToSocketAddrs;
+use std::time::Instant;
 
-use crate::A2S;
 use crate::errors::Error;
+use crate::info::Info;
+use crate::lenient::{Parser, Warning, is_exhausted};
+use crate::protocol::{QueryKind, QueryOutcome, Response};
+use crate::transport::Transport;
+use crate::utils::read_bytes::ReadBytes;
 use crate::utils::read_string::ReadString;
+use crate::utils::write_string::WriteString;
+use crate::{A2S, HEADER_SINGLE_PACKET, single_packet_payload};
+
+#[cfg(feature = "tokio")]
+use crate::AsyncA2S;
 
 // A2S_PLAYER
-const PLAYER_REQUEST_HEADER: u8 = 0x55;
-const PLAYER_RESPONSE_HEADER: u8 = 0x44;
+pub(crate) const PLAYER_REQUEST_HEADER: u8 = 0x55;
+pub(crate) const PLAYER_RESPONSE_HEADER: u8 = 0x44;
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     /// Index of player chunk starting from 0
     pub index: u8,
@@ -30,7 +40,7 @@ pub struct Player {
     pub the_ship: Option<TheShipInfo>,
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TheShipInfo {
     /// Player's deaths
     pub deaths: u32,
@@ -49,9 +59,29 @@ impl Player {
         }
     }
 
-    fn list_from_bytes(
+    fn from_bytes(payload: &mut Cursor<Vec<u8>>, is_the_ship: bool) -> Result<Self, Error> {
+        Ok(Player {
+            index: payload.read_u8()?,
+            name: payload.read_string()?,
+            score: payload.read_i32::<LittleEndian>()?,
+            duration: payload.read_f32::<LittleEndian>()?,
+            the_ship: if is_the_ship {
+                Some(TheShipInfo {
+                    deaths: payload.read_u32::<LittleEndian>()?,
+                    money: payload.read_u32::<LittleEndian>()?,
+                })
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Parse the players. In lenient mode, every complete entry is kept whatever
+    /// number of players was announced.
+    pub(crate) fn list_from_bytes(
         mut payload: Cursor<Vec<u8>>,
         is_the_ship: bool,
+        parser: &mut Parser,
     ) -> Result<Vec<Self>, Error> {
         let header = payload.read_u8()?;
         if header != PLAYER_RESPONSE_HEADER {
@@ -61,40 +91,159 @@ impl Player {
             });
         }
 
-        let total_players = payload.read_u8()?;
-        let mut players: Vec<Player> = Vec::with_capacity(total_players as usize);
-
-        for _ in 0..total_players {
-            players.push(Player {
-                index: payload.read_u8()?,
-                name: payload.read_string()?,
-                score: payload.read_i32::<LittleEndian>()?,
-                duration: payload.read_f32::<LittleEndian>()?,
-                the_ship: if is_the_ship {
-                    Some(TheShipInfo {
-                        deaths: payload.read_u32::<LittleEndian>()?,
-                        money: payload.read_u32::<LittleEndian>()?,
-                    })
-                } else {
-                    None
-                },
-            });
+        let total_players = payload.read_u8()? as usize;
+        let mut players: Vec<Player> = Vec::with_capacity(total_players);
+
+        loop {
+            let done = if parser.is_lenient() {
+                is_exhausted(&payload)
+            } else {
+                players.len() == total_players
+            };
+            if done {
+                break;
+            }
+
+            let player = Player::from_bytes(&mut payload, is_the_ship).map(Some);
+            match parser.field("players", player)? {
+                Some(player) => players.push(player),
+                None => break,
+            }
 
-            if (payload.position() as usize + Player::min_byte_size(is_the_ship))
-                > payload.get_ref().len()
+            if !parser.is_lenient()
+                && (payload.position() as usize + Player::min_byte_size(is_the_ship))
+                    > payload.get_ref().len()
             {
                 break;
             }
         }
 
+        if players.len() != total_players {
+            parser.warn(Warning::CountMismatch {
+                expected: total_players,
+                found: players.len(),
+            });
+        }
         Ok(players)
     }
 }
 
-impl<A: ToSocketAddrs> A2S<A> {
+impl Player {
+    fn write_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(self.index);
+        buf.write_string(&self.name);
+        buf.extend_from_slice(&self.score.to_le_bytes());
+        buf.extend_from_slice(&self.duration.to_le_bytes());
+        if let Some(the_ship) = &self.the_ship {
+            buf.extend_from_slice(&the_ship.deaths.to_le_bytes());
+            buf.extend_from_slice(&the_ship.money.to_le_bytes());
+        }
+    }
+
+    /// Decode a single packet players response, such as one made by
+    /// [`Player::encode_list`]. The Ship sends additional info for each player.
+    pub fn decode_list(datagram: &[u8], is_the_ship: bool) -> Result<Vec<Self>, Error> {
+        let payload = single_packet_payload(datagram)?;
+        Self::list_from_bytes(payload, is_the_ship, &mut Parser::new(false))
+    }
+
+    /// Decode a single packet players response, returning what could be decoded of
+    /// a malformed one along with the problems found
+    pub fn decode_list_lenient(
+        datagram: &[u8],
+        is_the_ship: bool,
+    ) -> Result<(Vec<Self>, Vec<Warning>), Error> {
+        let mut parser = Parser::new(true);
+        let payload = single_packet_payload(datagram)?;
+        let players = Self::list_from_bytes(payload, is_the_ship, &mut parser)?;
+        Ok((players, parser.into_warnings()))
+    }
+
+    /// Encode the players as a single packet response, keeping the first 255 as
+    /// the count is a single byte
+    pub fn encode_list(players: &[Player]) -> Vec<u8> {
+        let players = &players[..players.len().min(u8::MAX as usize)];
+        let mut buf = HEADER_SINGLE_PACKET.to_le_bytes().to_vec();
+        buf.extend_from_slice(&[PLAYER_RESPONSE_HEADER, players.len() as u8]);
+        for player in players {
+            player.write_bytes(&mut buf);
+        }
+        buf
+    }
+}
+
+impl<A: ToSocketAddrs, T: Transport> A2S<A, T> {
+    /// Query the players, fetching the server info first unless its app ID is
+    /// already known from a previous query
     pub fn players(&self) -> Result<Vec<Player>, Error> {
-        let is_the_ship = self.info()?.is_the_ship();
-        let data = self.send_and_recv_with_challenge(PLAYER_REQUEST_HEADER)?;
-        Player::list_from_bytes(data, is_the_ship)
+        self.players_outcome().map(|outcome| outcome.value)
+    }
+
+    /// Query the players like [`A2S::players`], along with measurements of the exchange
+    pub fn players_outcome(&self) -> Result<QueryOutcome<Vec<Player>>, Error> {
+        self.players_until(self.app_id(), self.config.deadline())
+    }
+
+    /// Query the players of a server whose info was already fetched
+    pub fn players_with_info(&self, info: &Info) -> Result<Vec<Player>, Error> {
+        self.players_for_app_id(info.app_id)
+    }
+
+    /// Query the players of a server running the game with the given app ID
+    pub fn players_for_app_id(&self, app_id: u32) -> Result<Vec<Player>, Error> {
+        self.players_until(Some(app_id), self.config.deadline())
+            .map(|outcome| outcome.value)
+    }
+
+    pub(crate) fn players_until(
+        &self,
+        app_id: Option<u32>,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Vec<Player>>, Error> {
+        let query = self.config.query(QueryKind::Players, app_id);
+        Ok(self.execute(query, deadline)?.map(into_players))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<A: tokio::net::ToSocketAddrs> AsyncA2S<A> {
+    /// Query the players, fetching the server info first unless its app ID is
+    /// already known from a previous query
+    pub async fn players(&self) -> Result<Vec<Player>, Error> {
+        self.players_outcome().await.map(|outcome| outcome.value)
+    }
+
+    /// Query the players like [`AsyncA2S::players`], along with measurements of the exchange
+    pub async fn players_outcome(&self) -> Result<QueryOutcome<Vec<Player>>, Error> {
+        self.players_until(self.app_id(), self.config.deadline())
+            .await
+    }
+
+    /// Query the players of a server whose info was already fetched
+    pub async fn players_with_info(&self, info: &Info) -> Result<Vec<Player>, Error> {
+        self.players_for_app_id(info.app_id).await
+    }
+
+    /// Query the players of a server running the game with the given app ID
+    pub async fn players_for_app_id(&self, app_id: u32) -> Result<Vec<Player>, Error> {
+        self.players_until(Some(app_id), self.config.deadline())
+            .await
+            .map(|outcome| outcome.value)
+    }
+
+    pub(crate) async fn players_until(
+        &self,
+        app_id: Option<u32>,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Vec<Player>>, Error> {
+        let query = self.config.query(QueryKind::Players, app_id);
+        Ok(self.execute(query, deadline).await?.map(into_players))
+    }
+}
+
+fn into_players(response: Response) -> Vec<Player> {
+    match response {
+        Response::Players(players) => players,
+        _ => unreachable!("players query answered with players"),
     }
 }
@@ -0,0 +1,635 @@
+//! Sans-IO state machine of a query, for driving A2S from any event loop.
+//!
+//! A [`Query`] tells which datagrams to send and parses the datagrams it is fed,
+//! handling challenges and split responses. Sockets, timers and retries are left
+//! to the caller:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), a2s_query::errors::Error> {
+//! use a2s_query::Engine;
+//! use a2s_query::protocol::{Output, Query, QueryKind};
+//!
+//! let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+//! socket.connect("127.0.0.1:27015")?;
+//!
+//! let mut query = Query::new(QueryKind::Info, Engine::Source);
+//! socket.send(query.request())?;
+//!
+//! let mut buf = [0u8; 1400];
+//! let response = loop {
+//!     let n_received = socket.recv(&mut buf)?;
+//!     match query.handle(&buf[..n_received])? {
+//!         Some(Output::Send(request)) => socket.send(&request).map(|_| ())?,
+//!         Some(Output::Done(response)) => break response,
+//!         None => {}
+//!     }
+//! };
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use crate::errors::Error;
+use crate::info::{Info, info_request};
+use crate::lenient::{Parser, Warning};
+use crate::ping::{PING_REQUEST_HEADER, PING_RESPONSE_HEADER, PingMethod};
+use crate::players::{PLAYER_REQUEST_HEADER, Player};
+use crate::rules::{RULES_REQUEST_HEADER, Rule};
+use crate::utils::read_bytes::ReadBytes;
+use crate::{
+    Collector, Engine, HEADER_MULTI_PACKET, HEADER_SINGLE_PACKET, PacketAssembler, Quirks,
+    S2C_CHALLENGE,
+};
+use crate::{challenge_request, read_challenge};
+
+/// Kind of query sent to a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Info,
+    Players,
+    Rules,
+}
+
+/// Parsed answer to a query
+#[derive(Debug)]
+pub enum Response {
+    Info(Box<Info>),
+    Players(Vec<Player>),
+    Rules(Vec<Rule>),
+}
+
+/// What to do after feeding a datagram to a query
+#[derive(Debug)]
+pub enum Output {
+    /// Send this request to the server and keep feeding the answers
+    Send(Vec<u8>),
+
+    /// The query is complete
+    Done(Response),
+}
+
+/// Stage of a query, telling where it failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Resolving the server address and opening a socket to it
+    Connect,
+
+    /// Fetching the server info, which a players query needs to parse players
+    Info,
+
+    /// Waiting for a challenge
+    Challenge,
+
+    /// Waiting for the response
+    Request,
+
+    /// Parsing a complete response
+    Parse,
+}
+
+/// Result of a query along with measurements of the exchange with the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryOutcome<T> {
+    /// Parsed answer of the server
+    pub value: T,
+
+    /// Time between sending the last request and receiving the complete response
+    pub rtt: Duration,
+
+    /// Whether the server asked for a challenge
+    pub challenged: bool,
+
+    /// Number of datagrams received, challenges included
+    pub datagrams: usize,
+
+    /// Number of bytes received, challenges included
+    pub bytes: usize,
+
+    /// Whether the response was split in several packets
+    pub split: bool,
+
+    /// Whether the response was compressed
+    pub compressed: bool,
+
+    /// Address of the server which answered, unless unknown to the transport
+    pub peer: Option<SocketAddr>,
+
+    /// Problems found in the responses, in lenient mode
+    pub warnings: Vec<Warning>,
+}
+
+impl<T> QueryOutcome<T> {
+    /// Convert the value, keeping the measurements
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> QueryOutcome<U> {
+        QueryOutcome {
+            value: f(self.value),
+            rtt: self.rtt,
+            challenged: self.challenged,
+            datagrams: self.datagrams,
+            bytes: self.bytes,
+            split: self.split,
+            compressed: self.compressed,
+            peer: self.peer,
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// Measurements of the exchange with the server, so far
+#[derive(Debug, Clone, Copy, Default)]
+struct Stats {
+    sent_at: Option<Instant>,
+    rtt: Duration,
+    challenged: bool,
+    datagrams: usize,
+    bytes: usize,
+    split: bool,
+    compressed: bool,
+}
+
+/// Number of challenges accepted for a request before giving up
+const MAX_CHALLENGES: usize = 5;
+
+/// Step of a players query, which needs to know the game before parsing players
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Info,
+    Request,
+}
+
+/// State of a query with one server
+pub struct Query {
+    kind: QueryKind,
+    step: Step,
+    stage: Stage,
+    /// Challenges received for the current step
+    challenges: usize,
+    /// Last challenge received
+    challenge: Option<i32>,
+    app_id: Option<u32>,
+    quirks: Option<Quirks>,
+    request: Vec<u8>,
+    assembler: PacketAssembler,
+    parser: Parser,
+    stats: Stats,
+}
+
+impl Query {
+    /// Start a query. A players query fetches the server info first, unless
+    /// the app ID is given with [`Query::with_app_id`].
+    pub fn new(kind: QueryKind, engine: Engine) -> Self {
+        let step = match kind {
+            QueryKind::Players => Step::Info,
+            QueryKind::Info | QueryKind::Rules => Step::Request,
+        };
+        Query {
+            kind,
+            step,
+            stage: initial_stage(kind, step),
+            challenges: 0,
+            challenge: None,
+            app_id: None,
+            quirks: None,
+            request: initial_request(kind, step),
+            assembler: PacketAssembler::new(engine, Quirks::default()),
+            parser: Parser::new(false),
+            stats: Stats::default(),
+        }
+    }
+
+    /// Set the app ID of the server, which players are parsed for and quirks
+    /// are looked up for. A players query no longer needs to fetch the server info.
+    pub fn with_app_id(mut self, app_id: u32) -> Self {
+        self.app_id = Some(app_id);
+        self.assembler.quirks = self.quirks();
+        if self.step == Step::Info {
+            self.next_step();
+        }
+        self
+    }
+
+    /// Request players right away even if the app ID is unknown, parsing them
+    /// as players of a game other than The Ship
+    pub(crate) fn without_info(mut self) -> Self {
+        if self.step == Step::Info {
+            self.next_step();
+        }
+        self
+    }
+
+    /// Set the quirks of the server, instead of looking them up by its app ID
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = Some(quirks);
+        self.assembler.quirks = quirks;
+        self
+    }
+
+    /// Return what could be parsed of malformed responses instead of failing,
+    /// recording the problems as [`Query::warnings`]
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.parser = Parser::new(lenient);
+        self
+    }
+
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    /// App ID of the server, once given or received with its info
+    pub fn app_id(&self) -> Option<u32> {
+        self.app_id
+    }
+
+    /// Stage the query is at
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Last challenge received from the server, which requests of other kinds
+    /// from the same address may reuse
+    pub fn challenge(&self) -> Option<i32> {
+        self.challenge
+    }
+
+    /// Quirks of the server, as set or looked up by its app ID once known
+    pub fn quirks(&self) -> Quirks {
+        Quirks::resolve(self.quirks, self.app_id)
+    }
+
+    /// Wrap the result of the completed query with its measurements. The round-trip
+    /// time is measured from the last call to [`Query::request`].
+    pub fn outcome<T>(&self, value: T, peer: Option<SocketAddr>) -> QueryOutcome<T> {
+        QueryOutcome {
+            value,
+            rtt: self.stats.rtt,
+            challenged: self.stats.challenged,
+            datagrams: self.stats.datagrams,
+            bytes: self.stats.bytes,
+            split: self.stats.split,
+            compressed: self.stats.compressed,
+            peer,
+            warnings: self.parser.warnings().to_vec(),
+        }
+    }
+
+    /// Problems found in the responses parsed so far, in lenient mode
+    pub fn warnings(&self) -> &[Warning] {
+        self.parser.warnings()
+    }
+
+    /// Error to report once the server stopped answering: which packet is missing
+    /// if it stopped in the middle of a split response, or else a timeout
+    pub fn timeout_error(&self) -> Error {
+        self.assembler.missing_fragment().unwrap_or(Error::Timeout)
+    }
+
+    /// Request to send for the current step, to start the query or to retry after
+    /// the server did not answer in time. Parts of a previous answer are discarded.
+    pub fn request(&mut self) -> &[u8] {
+        self.reset();
+        &self.request
+    }
+
+    fn reset(&mut self) {
+        self.assembler.reset();
+        self.stats.sent_at = Some(Instant::now());
+    }
+
+    /// Feed a datagram received from the server. Returns `None` while more
+    /// datagrams are needed to complete the current response.
+    pub fn handle(&mut self, datagram: &[u8]) -> Result<Option<Output>, Error> {
+        self.stats.datagrams += 1;
+        self.stats.bytes += datagram.len();
+
+        // Challenges are never split, so the server answered without one
+        if self.stage == Stage::Challenge
+            && datagram.starts_with(&HEADER_MULTI_PACKET.to_le_bytes())
+        {
+            self.stage = Stage::Request;
+        }
+
+        let Some(payload) = self.assembler.push(datagram)? else {
+            return Ok(None);
+        };
+        self.stats.rtt = self
+            .stats
+            .sent_at
+            .map(|sent_at| sent_at.elapsed())
+            .unwrap_or_default();
+        self.stats.split = self.assembler.answer_id.is_some();
+        self.stats.compressed = self.assembler.compression.is_some();
+        self.handle_payload(payload)
+    }
+
+    /// Answer a challenge or parse a complete payload
+    fn handle_payload(&mut self, mut payload: Cursor<Vec<u8>>) -> Result<Option<Output>, Error> {
+        if let Some(challenge) = read_challenge(&mut payload)? {
+            self.challenges += 1;
+            self.challenge = Some(challenge);
+            self.stats.challenged = true;
+            if self.challenges > MAX_CHALLENGES {
+                return Err(Error::ChallengeFailed);
+            }
+            if self.stage == Stage::Challenge {
+                self.stage = Stage::Request;
+            }
+            self.request = match (self.kind, self.step) {
+                (QueryKind::Info, _) | (_, Step::Info) => info_request(Some(challenge)),
+                (QueryKind::Players, Step::Request) => {
+                    challenge_request(PLAYER_REQUEST_HEADER, challenge)
+                }
+                (QueryKind::Rules, Step::Request) => {
+                    challenge_request(RULES_REQUEST_HEADER, challenge)
+                }
+            };
+            return Ok(Some(self.next_request()));
+        }
+
+        self.stage = Stage::Parse;
+        match (self.kind, self.step) {
+            (QueryKind::Info, _) => {
+                let info = Info::from_bytes(payload, &mut self.parser)?;
+                self.app_id = Some(info.app_id);
+                Ok(Some(Output::Done(Response::Info(Box::new(info)))))
+            }
+            (QueryKind::Players, Step::Info) => {
+                let info = Info::from_bytes(payload, &mut self.parser)?;
+                self.app_id = Some(info.app_id);
+                self.assembler.quirks = self.quirks();
+                self.next_step();
+                Ok(Some(self.next_request()))
+            }
+            (QueryKind::Players, Step::Request) => {
+                let is_the_ship = self.app_id.is_some_and(Info::is_the_ship_app_id);
+                let players = Player::list_from_bytes(payload, is_the_ship, &mut self.parser)?;
+                Ok(Some(Output::Done(Response::Players(players))))
+            }
+            (QueryKind::Rules, _) => {
+                let rules = Rule::list_from_bytes(payload, &mut self.parser)?;
+                Ok(Some(Output::Done(Response::Rules(rules))))
+            }
+        }
+    }
+
+    fn next_request(&mut self) -> Output {
+        Output::Send(self.request().to_vec())
+    }
+
+    /// Move on from fetching the info to requesting players
+    fn next_step(&mut self) {
+        self.step = Step::Request;
+        self.stage = initial_stage(self.kind, self.step);
+        self.challenges = 0;
+        self.request = initial_request(self.kind, self.step);
+    }
+}
+
+impl Collector for Query {
+    type Output = Output;
+
+    fn reset(&mut self) {
+        Query::reset(self);
+    }
+
+    fn push(&mut self, datagram: &[u8]) -> Result<Option<Output>, Error> {
+        self.handle(datagram)
+    }
+
+    fn missing_fragment(&self) -> Option<Error> {
+        self.assembler.missing_fragment()
+    }
+}
+
+/// Request sent as part of a batch, with the headers its response may start with
+struct BatchRequest {
+    request: Vec<u8>,
+    response_headers: &'static [u8],
+    response: Option<Cursor<Vec<u8>>>,
+}
+
+/// Requests sent together, whose responses are told apart by their header. The
+/// batch is complete once every request got either its response or a challenge.
+pub(crate) struct Batch {
+    engine: Engine,
+    quirks: Quirks,
+    requests: Vec<BatchRequest>,
+    /// Split responses being reassembled, by answer ID
+    assemblers: HashMap<i32, PacketAssembler>,
+    /// Number of challenges received, which cannot be matched with their request
+    challenges: usize,
+}
+
+impl Batch {
+    pub(crate) fn new(engine: Engine, quirks: Quirks) -> Self {
+        Batch {
+            engine,
+            quirks,
+            requests: Vec::new(),
+            assemblers: HashMap::new(),
+            challenges: 0,
+        }
+    }
+
+    /// Add a request to the batch, returning its index
+    pub(crate) fn add(&mut self, request: Vec<u8>, response_headers: &'static [u8]) -> usize {
+        self.requests.push(BatchRequest {
+            request,
+            response_headers,
+            response: None,
+        });
+        self.requests.len() - 1
+    }
+
+    /// Number of requests answered with a challenge instead of their response
+    pub(crate) fn challenges(&self) -> usize {
+        self.challenges
+    }
+
+    /// Take the response to the request at the given index, if received
+    pub(crate) fn take(&mut self, index: usize) -> Option<Cursor<Vec<u8>>> {
+        self.requests[index].response.take()
+    }
+
+    /// Requests still waiting for a response
+    fn pending(&self) -> impl Iterator<Item = &[u8]> {
+        self.requests
+            .iter()
+            .filter(|r| r.response.is_none())
+            .map(|r| r.request.as_slice())
+    }
+
+    fn is_done(&self) -> bool {
+        self.pending().count() <= self.challenges
+    }
+
+    /// Hand a complete payload to the first pending request it may answer
+    fn route(&mut self, payload: Cursor<Vec<u8>>) {
+        let Some(&header) = payload.get_ref().get(payload.position() as usize) else {
+            return;
+        };
+
+        if header == S2C_CHALLENGE {
+            self.challenges += 1;
+            return;
+        }
+
+        if let Some(request) = self
+            .requests
+            .iter_mut()
+            .find(|r| r.response.is_none() && r.response_headers.contains(&header))
+        {
+            request.response = Some(payload);
+        }
+    }
+}
+
+impl Collector for Batch {
+    type Output = ();
+
+    /// Challenges received by an earlier attempt do not count toward this one
+    fn reset(&mut self) {
+        self.assemblers.clear();
+        self.challenges = 0;
+    }
+
+    /// Only the requests still waiting for a response are sent again
+    fn requests<'a>(&'a self, _request: &'a [u8]) -> Vec<&'a [u8]> {
+        self.pending().collect()
+    }
+
+    /// Datagrams which do not belong to any request are ignored
+    fn push(&mut self, datagram: &[u8]) -> Result<Option<()>, Error> {
+        let header = datagram
+            .get(..4)
+            .map(|h| i32::from_le_bytes(h.try_into().unwrap()));
+        let answer_id = datagram
+            .get(4..8)
+            .map(|id| i32::from_le_bytes(id.try_into().unwrap()));
+
+        let res = match (header, answer_id) {
+            (Some(HEADER_MULTI_PACKET), Some(answer_id)) => {
+                let (engine, quirks) = (self.engine, self.quirks);
+                let res = self
+                    .assemblers
+                    .entry(answer_id)
+                    .or_insert_with(|| PacketAssembler::new(engine, quirks))
+                    .push(datagram);
+                if !matches!(res, Ok(None)) {
+                    self.assemblers.remove(&answer_id);
+                }
+                res
+            }
+            _ => PacketAssembler::new(self.engine, self.quirks).push(datagram),
+        };
+
+        if let Ok(Some(payload)) = res {
+            self.route(payload);
+        }
+        Ok(self.is_done().then_some(()))
+    }
+
+    fn missing_fragment(&self) -> Option<Error> {
+        self.assemblers
+            .values()
+            .find_map(PacketAssembler::missing_fragment)
+    }
+}
+
+/// Exchange measuring the round-trip time to a server, complete once a full
+/// answer is received
+pub(crate) struct Ping {
+    method: PingMethod,
+    request: Vec<u8>,
+    stage: Stage,
+    assembler: PacketAssembler,
+    sent_at: Option<Instant>,
+}
+
+impl Ping {
+    pub(crate) fn new(method: PingMethod, engine: Engine, quirks: Quirks) -> Self {
+        let request = match method {
+            PingMethod::A2APing => {
+                let mut request = HEADER_SINGLE_PACKET.to_le_bytes().to_vec();
+                request.push(PING_REQUEST_HEADER);
+                request
+            }
+            PingMethod::Challenge => challenge_request(PLAYER_REQUEST_HEADER, HEADER_SINGLE_PACKET),
+        };
+        Ping {
+            method,
+            request,
+            stage: Stage::Request,
+            assembler: PacketAssembler::new(engine, quirks),
+            sent_at: None,
+        }
+    }
+
+    pub(crate) fn request(&self) -> &[u8] {
+        &self.request
+    }
+
+    pub(crate) fn stage(&self) -> Stage {
+        self.stage
+    }
+}
+
+impl Collector for Ping {
+    type Output = Duration;
+
+    fn reset(&mut self) {
+        self.assembler.reset();
+        self.sent_at = Some(Instant::now());
+    }
+
+    /// A challenge request is answered by either a challenge or players, which
+    /// are left unparsed. Returns the time elapsed since the request was sent.
+    fn push(&mut self, datagram: &[u8]) -> Result<Option<Duration>, Error> {
+        let Some(mut payload) = self.assembler.push(datagram)? else {
+            return Ok(None);
+        };
+        let rtt = self
+            .sent_at
+            .map(|sent_at| sent_at.elapsed())
+            .unwrap_or_default();
+
+        self.stage = Stage::Parse;
+        if self.method == PingMethod::A2APing {
+            let header = payload.read_u8()?;
+            if header != PING_RESPONSE_HEADER {
+                return Err(Error::InvalidHeader {
+                    expected: PING_RESPONSE_HEADER,
+                    found: header,
+                });
+            }
+        }
+        Ok(Some(rtt))
+    }
+
+    fn missing_fragment(&self) -> Option<Error> {
+        self.assembler.missing_fragment()
+    }
+}
+
+fn initial_stage(kind: QueryKind, step: Step) -> Stage {
+    match (kind, step) {
+        (_, Step::Info) => Stage::Info,
+        (QueryKind::Info, _) => Stage::Request,
+        (QueryKind::Players | QueryKind::Rules, Step::Request) => Stage::Challenge,
+    }
+}
+
+fn initial_request(kind: QueryKind, step: Step) -> Vec<u8> {
+    match (kind, step) {
+        (QueryKind::Info, _) | (_, Step::Info) => info_request(None),
+        (QueryKind::Players, Step::Request) => {
+            challenge_request(PLAYER_REQUEST_HEADER, HEADER_SINGLE_PACKET)
+        }
+        (QueryKind::Rules, Step::Request) => {
+            challenge_request(RULES_REQUEST_HEADER, HEADER_SINGLE_PACKET)
+        }
+    }
+}
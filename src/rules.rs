@@ -1,17 +1,26 @@
-use byteorder::{LittleEndian, ReadBytesExt};
-use serde::Serialize;
+use byteorder::LittleEndian;
+use serde::{Deserialize, Serialize};
 use std::io::Cursor;
 use std::net::ToSocketAddrs;
+use std::time::Instant;
 
-use crate::A2S;
 use crate::errors::Error;
+use crate::lenient::{Parser, Warning, is_exhausted};
+use crate::protocol::{QueryKind, QueryOutcome, Response};
+use crate::transport::Transport;
+use crate::utils::read_bytes::ReadBytes;
 use crate::utils::read_string::ReadString;
+use crate::utils::write_string::WriteString;
+use crate::{A2S, HEADER_SINGLE_PACKET, single_packet_payload};
+
+#[cfg(feature = "tokio")]
+use crate::AsyncA2S;
 
 // A2S_RULES
-const RULES_REQUEST_HEADER: u8 = 0x56;
-const RULES_RESPONSE_HEADER: u8 = 0x45;
+pub(crate) const RULES_REQUEST_HEADER: u8 = 0x56;
+pub(crate) const RULES_RESPONSE_HEADER: u8 = 0x45;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Rule {
     pub name: String,
 
@@ -19,7 +28,12 @@ pub struct Rule {
 }
 
 impl Rule {
-    fn list_from_bytes(mut payload: Cursor<Vec<u8>>) -> Result<Vec<Rule>, Error> {
+    /// Parse the rules. In lenient mode, the rules are read until the end of the
+    /// payload whatever number of rules was announced.
+    pub(crate) fn list_from_bytes(
+        mut payload: Cursor<Vec<u8>>,
+        parser: &mut Parser,
+    ) -> Result<Vec<Rule>, Error> {
         let header = payload.read_u8()?;
         if header != RULES_RESPONSE_HEADER {
             return Err(Error::InvalidHeader {
@@ -28,23 +42,110 @@ impl Rule {
             });
         }
 
-        let n_rules = payload.read_u16::<LittleEndian>()?;
-        let mut rules: Vec<Rule> = Vec::with_capacity(n_rules as usize);
+        let n_rules = payload.read_u16::<LittleEndian>()? as usize;
+        let mut rules: Vec<Rule> = Vec::with_capacity(n_rules);
+
+        if !parser.is_lenient() {
+            for _ in 0..n_rules {
+                rules.push(Rule {
+                    name: payload.read_string()?,
+                    value: payload.read_string()?,
+                });
+            }
+            return Ok(rules);
+        }
 
-        for _ in 0..n_rules {
+        while !is_exhausted(&payload) {
+            let name = payload.read_string()?;
+            if is_exhausted(&payload) {
+                parser.warn(Warning::Truncated { field: "rules" });
+                break;
+            }
             rules.push(Rule {
-                name: payload.read_string()?,
+                name,
                 value: payload.read_string()?,
             });
         }
 
+        if rules.len() != n_rules {
+            parser.warn(Warning::CountMismatch {
+                expected: n_rules,
+                found: rules.len(),
+            });
+        }
         Ok(rules)
     }
+
+    /// Decode a single packet rules response, such as one made by [`Rule::encode_list`]
+    pub fn decode_list(datagram: &[u8]) -> Result<Vec<Self>, Error> {
+        Self::list_from_bytes(single_packet_payload(datagram)?, &mut Parser::new(false))
+    }
+
+    /// Decode a single packet rules response, returning what could be decoded of a
+    /// malformed one along with the problems found
+    pub fn decode_list_lenient(datagram: &[u8]) -> Result<(Vec<Self>, Vec<Warning>), Error> {
+        let mut parser = Parser::new(true);
+        let rules = Self::list_from_bytes(single_packet_payload(datagram)?, &mut parser)?;
+        Ok((rules, parser.into_warnings()))
+    }
+
+    /// Encode the rules as a single packet response, keeping the first 65535 as
+    /// the count is 16 bits
+    pub fn encode_list(rules: &[Rule]) -> Vec<u8> {
+        let rules = &rules[..rules.len().min(u16::MAX as usize)];
+        let mut buf = HEADER_SINGLE_PACKET.to_le_bytes().to_vec();
+        buf.push(RULES_RESPONSE_HEADER);
+        buf.extend_from_slice(&(rules.len() as u16).to_le_bytes());
+        for rule in rules {
+            buf.write_string(&rule.name);
+            buf.write_string(&rule.value);
+        }
+        buf
+    }
 }
 
-impl<A: ToSocketAddrs> A2S<A> {
+impl<A: ToSocketAddrs, T: Transport> A2S<A, T> {
     pub fn rules(&self) -> Result<Vec<Rule>, Error> {
-        let data = self.send_and_recv_with_challenge(RULES_REQUEST_HEADER)?;
-        Rule::list_from_bytes(data)
+        self.rules_outcome().map(|outcome| outcome.value)
+    }
+
+    /// Query the rules, along with measurements of the exchange
+    pub fn rules_outcome(&self) -> Result<QueryOutcome<Vec<Rule>>, Error> {
+        self.rules_until(self.config.deadline())
+    }
+
+    pub(crate) fn rules_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Vec<Rule>>, Error> {
+        let query = self.config.query(QueryKind::Rules, self.app_id());
+        Ok(self.execute(query, deadline)?.map(into_rules))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<A: tokio::net::ToSocketAddrs> AsyncA2S<A> {
+    pub async fn rules(&self) -> Result<Vec<Rule>, Error> {
+        self.rules_outcome().await.map(|outcome| outcome.value)
+    }
+
+    /// Query the rules, along with measurements of the exchange
+    pub async fn rules_outcome(&self) -> Result<QueryOutcome<Vec<Rule>>, Error> {
+        self.rules_until(self.config.deadline()).await
+    }
+
+    pub(crate) async fn rules_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<QueryOutcome<Vec<Rule>>, Error> {
+        let query = self.config.query(QueryKind::Rules, self.app_id());
+        Ok(self.execute(query, deadline).await?.map(into_rules))
+    }
+}
+
+fn into_rules(response: Response) -> Vec<Rule> {
+    match response {
+        Response::Rules(rules) => rules,
+        _ => unreachable!("rules query answered with rules"),
     }
 }
@@ -0,0 +1,282 @@
+//! Answering A2S queries, for game servers to show up in server browsers.
+//!
+//! A [`Responder`] holds the info, players and rules of a server and builds the
+//! datagrams answering each request, handing out challenges and splitting large
+//! responses. Sockets are left to the caller, or to [`Responder::serve_one`]:
+//!
+//! ```no_run
+//! # fn serve(info: a2s_query::info::Info) -> Result<(), a2s_query::errors::Error> {
+//! use std::net::UdpSocket;
+//! use a2s_query::server::Responder;
+//!
+//! let socket = UdpSocket::bind("0.0.0.0:27015")?;
+//! let mut responder = Responder::new(info).with_compression(true);
+//!
+//! let mut buf = [0u8; 1400];
+//! loop {
+//!     let (n_received, peer) = socket.recv_from(&mut buf)?;
+//!     let Ok(datagrams) = responder.handle(&buf[..n_received], peer) else {
+//!         continue;
+//!     };
+//!     for datagram in datagrams {
+//!         socket.send_to(&datagram, peer)?;
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{Cursor, Write};
+use std::net::{SocketAddr, UdpSocket};
+
+use byteorder::LittleEndian;
+use bzip2::write::BzEncoder;
+
+use crate::errors::Error;
+use crate::info::{INFO_REQUEST_HEADER, INFO_REQUEST_PAYLOAD, Info};
+use crate::lenient::is_exhausted;
+use crate::players::{PLAYER_REQUEST_HEADER, Player};
+use crate::rules::{RULES_REQUEST_HEADER, Rule};
+use crate::utils::read_bytes::ReadBytes;
+use crate::utils::read_string::ReadString;
+use crate::{
+    COMPRESSED_ANSWER_ID_FLAG, Compression, Engine, HEADER_MULTI_PACKET, HEADER_SINGLE_PACKET,
+    MAX_DATAGRAM_SIZE, MAX_PACKETS, Quirks, S2C_CHALLENGE, challenge_request,
+};
+
+/// Size of the datagrams sent by Source servers, beyond which responses are split
+const DEFAULT_MAX_PACKET_SIZE: usize = 1248;
+/// Smallest datagram size, leaving room for split and compression headers
+const MIN_PACKET_SIZE: usize = 64;
+/// Largest number of packets of a GoldSource split response, counted on 4 bits
+const MAX_GOLDSRC_PACKETS: u8 = 15;
+
+/// Server side of A2S, answering queries with the given info, players and rules
+pub struct Responder {
+    info: Info,
+    players: Vec<Player>,
+    rules: Vec<Rule>,
+    engine: Engine,
+    quirks: Quirks,
+    max_packet_size: usize,
+    compression: bool,
+    info_challenge: bool,
+    /// Key the challenge of each client is derived from
+    secret: RandomState,
+    /// ID of the last split response
+    answer_id: u32,
+}
+
+impl Responder {
+    /// Answer queries with the given info, and no players or rules until set
+    pub fn new(info: Info) -> Self {
+        Responder {
+            info,
+            players: Vec::new(),
+            rules: Vec::new(),
+            engine: Engine::Source,
+            quirks: Quirks::default(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            compression: false,
+            info_challenge: true,
+            secret: RandomState::new(),
+            answer_id: 0,
+        }
+    }
+
+    pub fn with_players(mut self, players: Vec<Player>) -> Self {
+        self.players = players;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Vec<Rule>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Set the engine whose split packet format is used
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Set the quirks of the game, to leave out the size field of split packet
+    /// headers like games of the 2006 Source engine and older
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Set the size of the datagrams, beyond which responses are split. It is
+    /// kept between 64 and 32767 bytes.
+    pub fn with_max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size.clamp(MIN_PACKET_SIZE, i16::MAX as usize);
+        self
+    }
+
+    /// Compress split responses with bzip2, which only Source supports
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Whether info requests need a challenge like players and rules requests,
+    /// as servers updated since 2020 do. Enabled by default.
+    pub fn with_info_challenge(mut self, info_challenge: bool) -> Self {
+        self.info_challenge = info_challenge;
+        self
+    }
+
+    pub fn info(&self) -> &Info {
+        &self.info
+    }
+
+    pub fn set_info(&mut self, info: Info) {
+        self.info = info;
+    }
+
+    pub fn set_players(&mut self, players: Vec<Player>) {
+        self.players = players;
+    }
+
+    pub fn set_rules(&mut self, rules: Vec<Rule>) {
+        self.rules = rules;
+    }
+
+    /// Datagrams answering a request received from the given client: a challenge
+    /// if the request lacks the right one, or else the possibly split response
+    pub fn handle(&mut self, request: &[u8], peer: SocketAddr) -> Result<Vec<Vec<u8>>, Error> {
+        let mut request = Cursor::new(request.to_vec());
+        if request.read_i32::<LittleEndian>()? != HEADER_SINGLE_PACKET {
+            return Err(Error::InvalidRequest);
+        }
+
+        let challenge = self.challenge(peer);
+        let response = match request.read_u8()? {
+            INFO_REQUEST_HEADER => {
+                if request.read_string()? != INFO_REQUEST_PAYLOAD.trim_end_matches('\0') {
+                    return Err(Error::InvalidRequest);
+                }
+                let received = if is_exhausted(&request) {
+                    None
+                } else {
+                    Some(request.read_i32::<LittleEndian>()?)
+                };
+                if self.info_challenge && received != Some(challenge) {
+                    return Ok(vec![challenge_request(S2C_CHALLENGE, challenge)]);
+                }
+                self.info.encode()
+            }
+            header @ (PLAYER_REQUEST_HEADER | RULES_REQUEST_HEADER) => {
+                if request.read_i32::<LittleEndian>()? != challenge {
+                    return Ok(vec![challenge_request(S2C_CHALLENGE, challenge)]);
+                }
+                if header == PLAYER_REQUEST_HEADER {
+                    Player::encode_list(&self.players)
+                } else {
+                    Rule::encode_list(&self.rules)
+                }
+            }
+            _ => return Err(Error::InvalidRequest),
+        };
+
+        if response.len() <= self.max_packet_size {
+            return Ok(vec![response]);
+        }
+        self.split(response)
+    }
+
+    /// Receive a request on the socket and answer it. Invalid requests are ignored,
+    /// only I/O errors are returned.
+    pub fn serve_one(&mut self, socket: &UdpSocket) -> Result<(), Error> {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+        let (n_received, peer) = socket.recv_from(&mut buf)?;
+        match self.handle(&buf[..n_received], peer) {
+            Ok(datagrams) => {
+                for datagram in datagrams {
+                    socket.send_to(&datagram, peer)?;
+                }
+                Ok(())
+            }
+            Err(Error::Io(e)) => Err(Error::Io(e)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Challenge expected from the client, derived from its address so that none
+    /// has to be remembered. It never is -1, which asks for a challenge.
+    fn challenge(&self, peer: SocketAddr) -> i32 {
+        match self.secret.hash_one(peer) as i32 {
+            HEADER_SINGLE_PACKET => 0,
+            challenge => challenge,
+        }
+    }
+
+    /// Split a response in packets of the engine format, compressing it first if enabled
+    fn split(&mut self, response: Vec<u8>) -> Result<Vec<Vec<u8>>, Error> {
+        self.answer_id = self.answer_id.wrapping_add(1) & !COMPRESSED_ANSWER_ID_FLAG;
+        let mut answer_id = self.answer_id;
+
+        let compression =
+            (self.compression && self.engine == Engine::Source).then(|| Compression {
+                decompressed_size: response.len() as u32,
+                crc32: crc32fast::hash(&response),
+            });
+        let body = match compression {
+            Some(_) => {
+                answer_id |= COMPRESSED_ANSWER_ID_FLAG;
+                let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::best());
+                encoder.write_all(&response)?;
+                encoder.finish()?
+            }
+            None => response,
+        };
+
+        let (header_size, max_packets) = match self.engine {
+            Engine::Source if self.quirks.split_size_field => (12, MAX_PACKETS),
+            Engine::Source => (10, MAX_PACKETS),
+            Engine::GoldSource => (9, MAX_GOLDSRC_PACKETS),
+        };
+        let chunk_size = self.max_packet_size - header_size;
+        // The first packet also carries the size and checksum of a compressed response
+        let first_size = match compression {
+            Some(_) => chunk_size - 8,
+            None => chunk_size,
+        };
+        let (first, rest) = body.split_at(first_size.min(body.len()));
+        let chunks: Vec<&[u8]> = std::iter::once(first)
+            .chain(rest.chunks(chunk_size))
+            .collect();
+        if chunks.len() > max_packets as usize {
+            return Err(Error::TooManyPackets {
+                total: chunks.len().min(u8::MAX as usize) as u8,
+                max: max_packets,
+            });
+        }
+
+        let total = chunks.len() as u8;
+        let packets = chunks.iter().enumerate().map(|(number, chunk)| {
+            let number = number as u8;
+            let mut packet = Vec::with_capacity(self.max_packet_size);
+            packet.extend_from_slice(&HEADER_MULTI_PACKET.to_le_bytes());
+            packet.extend_from_slice(&answer_id.to_le_bytes());
+            match self.engine {
+                Engine::Source => {
+                    packet.extend_from_slice(&[total, number]);
+                    if self.quirks.split_size_field {
+                        packet.extend_from_slice(&(self.max_packet_size as i16).to_le_bytes());
+                    }
+                }
+                Engine::GoldSource => packet.push(number << 4 | total),
+            }
+            if let (0, Some(compression)) = (number, &compression) {
+                packet.extend_from_slice(&compression.decompressed_size.to_le_bytes());
+                packet.extend_from_slice(&compression.crc32.to_le_bytes());
+            }
+            packet.extend_from_slice(chunk);
+            packet
+        });
+        Ok(packets.collect())
+    }
+}
@@ -0,0 +1,317 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::PoisonError;
+
+use crate::builder::Config;
+use crate::errors::Error;
+use crate::info::{GOLDSRC_INFO_RESPONSE_HEADER, INFO_RESPONSE_HEADER, Info, info_request};
+use crate::lenient::{Parser, Warning};
+use crate::players::{PLAYER_REQUEST_HEADER, PLAYER_RESPONSE_HEADER, Player};
+use crate::protocol::{Batch, Output, Query, QueryKind, Response, Stage};
+use crate::rules::{RULES_REQUEST_HEADER, RULES_RESPONSE_HEADER, Rule};
+use crate::transport::Transport;
+use crate::{A2S, HEADER_SINGLE_PACKET, challenge_request};
+
+#[cfg(feature = "tokio")]
+use crate::AsyncA2S;
+
+const INFO_RESPONSE_HEADERS: &[u8] = &[INFO_RESPONSE_HEADER, GOLDSRC_INFO_RESPONSE_HEADER];
+
+/// Info, players and rules of a server, each of which may have failed on its own
+#[derive(Debug)]
+pub struct ServerSnapshot {
+    pub info: Result<Info, Error>,
+
+    pub players: Result<Vec<Player>, Error>,
+
+    pub rules: Result<Vec<Rule>, Error>,
+
+    /// Problems found in the responses, in lenient mode
+    pub warnings: Vec<Warning>,
+}
+
+/// Outcome of the first step of a snapshot, asking for the challenge shared by all requests
+enum ChallengeStep {
+    Challenge(i32),
+    /// The server answered players right away, parsed for The Ship or another game
+    Players {
+        players: Vec<Player>,
+        is_the_ship: bool,
+        warnings: Vec<Warning>,
+    },
+    Failed(Error),
+}
+
+impl ChallengeStep {
+    fn new(res: Result<Output, Error>, query: &Query) -> Self {
+        match (res, query.challenge()) {
+            (Ok(Output::Done(Response::Players(players))), _) => ChallengeStep::Players {
+                players,
+                is_the_ship: query.app_id().is_some_and(Info::is_the_ship_app_id),
+                warnings: query.warnings().to_vec(),
+            },
+            (Ok(_), Some(challenge)) => ChallengeStep::Challenge(challenge),
+            (Ok(_), None) => ChallengeStep::Failed(Error::InvalidResponse),
+            (Err(e), _) => {
+                ChallengeStep::Failed(e.with_context(Some(QueryKind::Players), query.stage(), None))
+            }
+        }
+    }
+
+    fn challenge(&self) -> Option<i32> {
+        match self {
+            ChallengeStep::Challenge(challenge) => Some(*challenge),
+            _ => None,
+        }
+    }
+}
+
+/// Snapshot requests sent together once the challenge is known, whose responses
+/// are parsed in turn. Parts which have to be fetched with their own query, because
+/// the server asked for a new challenge or the batch failed, are returned as `None`.
+struct SnapshotBatch {
+    batch: Batch,
+    step: Option<ChallengeStep>,
+    info: usize,
+    players: Option<usize>,
+    rules: usize,
+    fallback: bool,
+    parser: Parser,
+}
+
+impl SnapshotBatch {
+    /// Batch the info and rules requests, plus the players one if it still has to be sent
+    fn new(config: &Config, app_id: Option<u32>, step: ChallengeStep) -> Self {
+        let challenge = step.challenge();
+        let mut batch = Batch::new(config.engine, config.quirks(app_id));
+
+        let info = batch.add(info_request(challenge), INFO_RESPONSE_HEADERS);
+        let players = challenge.map(|challenge| {
+            batch.add(
+                challenge_request(PLAYER_REQUEST_HEADER, challenge),
+                &[PLAYER_RESPONSE_HEADER],
+            )
+        });
+        let rules = batch.add(
+            challenge_request(
+                RULES_REQUEST_HEADER,
+                challenge.unwrap_or(HEADER_SINGLE_PACKET),
+            ),
+            &[RULES_RESPONSE_HEADER],
+        );
+
+        SnapshotBatch {
+            batch,
+            step: Some(step),
+            info,
+            players,
+            rules,
+            fallback: false,
+            parser: Parser::new(config.lenient),
+        }
+    }
+
+    /// Record how sending the batch went. Parts left unanswered after a timeout
+    /// fail, while other errors leave them to their own query.
+    fn sent(&mut self, res: Result<(), Error>) {
+        self.fallback = match res {
+            Ok(()) => self.batch.challenges() > 0,
+            Err(e) if e.is_timeout() => self.batch.challenges() > 0,
+            Err(_) => true,
+        };
+    }
+
+    fn info(&mut self) -> Option<Result<Info, Error>> {
+        match self.batch.take(self.info) {
+            Some(data) => Some(Info::from_bytes(data, &mut self.parser)),
+            None => self.unanswered(QueryKind::Info),
+        }
+    }
+
+    /// Parse players for the game with the given app ID
+    fn players(&mut self, app_id: Option<u32>) -> Option<Result<Vec<Player>, Error>> {
+        let is_the_ship = app_id.is_some_and(Info::is_the_ship_app_id);
+        let step = self.step.take().expect("players are parsed once");
+
+        let data = match (step, self.players) {
+            (
+                ChallengeStep::Players {
+                    players,
+                    is_the_ship: parsed_for_the_ship,
+                    warnings,
+                },
+                _,
+            ) => {
+                // Players parsed before the game was known are fetched again for The Ship
+                if parsed_for_the_ship != is_the_ship {
+                    return None;
+                }
+                for warning in warnings {
+                    self.parser.warn(warning);
+                }
+                return Some(Ok(players));
+            }
+            (ChallengeStep::Failed(e), _) => return Some(Err(e)),
+            (ChallengeStep::Challenge(_), Some(index)) => self.batch.take(index),
+            (ChallengeStep::Challenge(_), None) => None,
+        };
+
+        match data {
+            Some(data) => Some(Player::list_from_bytes(data, is_the_ship, &mut self.parser)),
+            None => self.unanswered(QueryKind::Players),
+        }
+    }
+
+    fn rules(&mut self) -> Option<Result<Vec<Rule>, Error>> {
+        match self.batch.take(self.rules) {
+            Some(data) => Some(Rule::list_from_bytes(data, &mut self.parser)),
+            None => self.unanswered(QueryKind::Rules),
+        }
+    }
+
+    fn unanswered<T>(&self, kind: QueryKind) -> Option<Result<T, Error>> {
+        match self.fallback {
+            true => None,
+            false => Some(Err(Error::Timeout.with_context(
+                Some(kind),
+                Stage::Request,
+                None,
+            ))),
+        }
+    }
+
+    /// Put the parts together, with the warnings of the fallback queries first
+    fn finish(
+        self,
+        info: Result<Info, Error>,
+        players: Result<Vec<Player>, Error>,
+        rules: Result<Vec<Rule>, Error>,
+        mut warnings: Vec<Warning>,
+        peer: Option<SocketAddr>,
+    ) -> ServerSnapshot {
+        warnings.append(&mut self.parser.into_warnings());
+
+        ServerSnapshot {
+            info: parse_context(info, QueryKind::Info, peer),
+            players: parse_context(players, QueryKind::Players, peer),
+            rules: parse_context(rules, QueryKind::Rules, peer),
+            warnings,
+        }
+    }
+}
+
+/// Attach the context of a part of the snapshot, failed while parsing unless
+/// another stage is already known
+fn parse_context<T>(
+    res: Result<T, Error>,
+    kind: QueryKind,
+    peer: Option<SocketAddr>,
+) -> Result<T, Error> {
+    res.map_err(|e| e.with_context(Some(kind), Stage::Parse, peer))
+}
+
+impl<A: ToSocketAddrs, T: Transport> A2S<A, T> {
+    /// Query info, players and rules at once.
+    ///
+    /// A single challenge is fetched and shared by all the requests, which are then
+    /// sent together. Parts answered with a new challenge fall back to their own query.
+    pub fn query_all(&self) -> ServerSnapshot {
+        let deadline = self.config.deadline();
+
+        let mut query = self
+            .config
+            .query(QueryKind::Players, self.app_id())
+            .without_info();
+        let request = query.request().to_vec();
+        let res = self.send_and_recv(&mut query, &request, deadline);
+        let step = ChallengeStep::new(res, &query);
+        let mut batch = SnapshotBatch::new(&self.config, self.app_id(), step);
+        let res = self.send_and_recv(&mut batch.batch, &[], deadline);
+        batch.sent(res);
+
+        let mut warnings = Vec::new();
+
+        let info = match batch.info() {
+            Some(info) => info,
+            None => self.info_until(deadline).map(|outcome| {
+                warnings.extend(outcome.warnings);
+                outcome.value
+            }),
+        };
+        if let Ok(info) = &info {
+            *self.app_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(info.app_id);
+        }
+
+        let app_id = self.app_id();
+        let players = match batch.players(app_id) {
+            Some(players) => players,
+            None => self.players_until(app_id, deadline).map(|outcome| {
+                warnings.extend(outcome.warnings);
+                outcome.value
+            }),
+        };
+
+        let rules = match batch.rules() {
+            Some(rules) => rules,
+            None => self.rules_until(deadline).map(|outcome| {
+                warnings.extend(outcome.warnings);
+                outcome.value
+            }),
+        };
+
+        batch.finish(info, players, rules, warnings, self.last_peer())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<A: tokio::net::ToSocketAddrs> AsyncA2S<A> {
+    /// Query info, players and rules at once.
+    ///
+    /// A single challenge is fetched and shared by all the requests, which are then
+    /// sent together. Parts answered with a new challenge fall back to their own query.
+    pub async fn query_all(&self) -> ServerSnapshot {
+        let deadline = self.config.deadline();
+
+        let mut query = self
+            .config
+            .query(QueryKind::Players, self.app_id())
+            .without_info();
+        let request = query.request().to_vec();
+        let res = self.send_and_recv(&mut query, &request, deadline).await;
+        let step = ChallengeStep::new(res, &query);
+        let mut batch = SnapshotBatch::new(&self.config, self.app_id(), step);
+        let res = self.send_and_recv(&mut batch.batch, &[], deadline).await;
+        batch.sent(res);
+
+        let mut warnings = Vec::new();
+
+        let info = match batch.info() {
+            Some(info) => info,
+            None => self.info_until(deadline).await.map(|outcome| {
+                warnings.extend(outcome.warnings);
+                outcome.value
+            }),
+        };
+        if let Ok(info) = &info {
+            *self.app_id.lock().unwrap_or_else(PoisonError::into_inner) = Some(info.app_id);
+        }
+
+        let app_id = self.app_id();
+        let players = match batch.players(app_id) {
+            Some(players) => players,
+            None => self.players_until(app_id, deadline).await.map(|outcome| {
+                warnings.extend(outcome.warnings);
+                outcome.value
+            }),
+        };
+
+        let rules = match batch.rules() {
+            Some(rules) => rules,
+            None => self.rules_until(deadline).await.map(|outcome| {
+                warnings.extend(outcome.warnings);
+                outcome.value
+            }),
+        };
+
+        batch.finish(info, players, rules, warnings, self.last_peer().await)
+    }
+}
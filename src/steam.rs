@@ -0,0 +1,291 @@
+//! Steam identifiers sent in the extra data of an info response
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Error;
+
+/// Kind of Steam account a [`SteamId`] belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountType {
+    Invalid,
+    Individual,
+    Multiseat,
+    GameServer,
+    AnonGameServer,
+    Pending,
+    ContentServer,
+    Clan,
+    Chat,
+    ConsoleUser,
+    AnonUser,
+    /// Raw value of an account type this crate does not know
+    Unknown(u8),
+}
+
+impl AccountType {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Invalid,
+            1 => Self::Individual,
+            2 => Self::Multiseat,
+            3 => Self::GameServer,
+            4 => Self::AnonGameServer,
+            5 => Self::Pending,
+            6 => Self::ContentServer,
+            7 => Self::Clan,
+            8 => Self::Chat,
+            9 => Self::ConsoleUser,
+            10 => Self::AnonUser,
+            _ => Self::Unknown(value),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Invalid => 0,
+            Self::Individual => 1,
+            Self::Multiseat => 2,
+            Self::GameServer => 3,
+            Self::AnonGameServer => 4,
+            Self::Pending => 5,
+            Self::ContentServer => 6,
+            Self::Clan => 7,
+            Self::Chat => 8,
+            Self::ConsoleUser => 9,
+            Self::AnonUser => 10,
+            Self::Unknown(value) => value,
+        }
+    }
+
+    /// Letter of the account type in the `[G:1:N]` text form
+    fn letter(self) -> char {
+        match self {
+            Self::Individual => 'U',
+            Self::Multiseat => 'M',
+            Self::GameServer => 'G',
+            Self::AnonGameServer => 'A',
+            Self::Pending => 'P',
+            Self::ContentServer => 'C',
+            Self::Clan => 'g',
+            Self::Chat => 'T',
+            Self::AnonUser => 'a',
+            Self::Invalid | Self::ConsoleUser | Self::Unknown(_) => 'I',
+        }
+    }
+
+    fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'I' => Some(Self::Invalid),
+            'U' => Some(Self::Individual),
+            'M' => Some(Self::Multiseat),
+            'G' => Some(Self::GameServer),
+            'A' => Some(Self::AnonGameServer),
+            'P' => Some(Self::Pending),
+            'C' => Some(Self::ContentServer),
+            'g' => Some(Self::Clan),
+            'T' | 'c' | 'L' => Some(Self::Chat),
+            'a' => Some(Self::AnonUser),
+            _ => None,
+        }
+    }
+
+    /// Instance left out of the `[G:1:N]` text form
+    fn default_instance(self) -> u32 {
+        match self {
+            Self::Individual => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// 64-bit SteamID of an account, such as the one of a game server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SteamId(u64);
+
+impl SteamId {
+    /// SteamID made of its parts, truncated to the bits they are stored on
+    pub fn new(universe: u8, account_type: AccountType, instance: u32, account_id: u32) -> Self {
+        SteamId(
+            (universe as u64) << 56
+                | ((account_type.to_u8() & 0xF) as u64) << 52
+                | ((instance & 0xF_FFFF) as u64) << 32
+                | account_id as u64,
+        )
+    }
+
+    /// Universe the account belongs to, 1 being the public one
+    pub fn universe(self) -> u8 {
+        (self.0 >> 56) as u8
+    }
+
+    pub fn account_type(self) -> AccountType {
+        AccountType::from_u8(((self.0 >> 52) & 0xF) as u8)
+    }
+
+    pub fn instance(self) -> u32 {
+        ((self.0 >> 32) & 0xF_FFFF) as u32
+    }
+
+    pub fn account_id(self) -> u32 {
+        self.0 as u32
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Legacy `STEAM_X:Y:Z` text form, only used for individual accounts
+    pub fn to_steam2(self) -> Option<String> {
+        (self.account_type() == AccountType::Individual).then(|| {
+            format!(
+                "STEAM_{}:{}:{}",
+                self.universe(),
+                self.account_id() & 1,
+                self.account_id() >> 1
+            )
+        })
+    }
+
+    fn parse_steam2(s: &str) -> Option<Self> {
+        let mut parts = s.strip_prefix("STEAM_")?.split(':');
+        let universe: u8 = parts.next()?.parse().ok()?;
+        let y: u32 = parts.next()?.parse().ok()?;
+        let z: u32 = parts.next()?.parse().ok()?;
+        if parts.next().is_some() || y > 1 || z > u32::MAX >> 1 {
+            return None;
+        }
+
+        // Old games report the public universe as 0
+        let universe = universe.max(1);
+        let account_type = AccountType::Individual;
+        let instance = account_type.default_instance();
+        Some(SteamId::new(universe, account_type, instance, z << 1 | y))
+    }
+
+    fn parse_steam3(s: &str) -> Option<Self> {
+        let mut parts = s.strip_prefix('[')?.strip_suffix(']')?.split(':');
+        let mut letter = parts.next()?.chars();
+        let account_type = AccountType::from_letter(letter.next()?)?;
+        if letter.next().is_some() {
+            return None;
+        }
+        let universe = parts.next()?.parse().ok()?;
+        let account_id = parts.next()?.parse().ok()?;
+        let instance = match parts.next() {
+            Some(instance) => instance.parse().ok()?,
+            None => account_type.default_instance(),
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(SteamId::new(universe, account_type, instance, account_id))
+    }
+}
+
+impl From<u64> for SteamId {
+    fn from(value: u64) -> Self {
+        SteamId(value)
+    }
+}
+
+impl From<SteamId> for u64 {
+    fn from(steam_id: SteamId) -> Self {
+        steam_id.0
+    }
+}
+
+/// `[G:1:N]` text form, followed by the instance if it is not the usual one
+impl fmt::Display for SteamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let account_type = self.account_type();
+        write!(
+            f,
+            "[{}:{}:{}",
+            account_type.letter(),
+            self.universe(),
+            self.account_id()
+        )?;
+        if self.instance() != account_type.default_instance() {
+            write!(f, ":{}", self.instance())?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Parse the `STEAM_X:Y:Z` or `[G:1:N]` text forms, or the 64-bit value
+impl FromStr for SteamId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .ok()
+            .map(SteamId)
+            .or_else(|| Self::parse_steam2(s))
+            .or_else(|| Self::parse_steam3(s))
+            .ok_or_else(|| Error::InvalidSteamId(s.to_string()))
+    }
+}
+
+/// Kind of game a [`GameId`] refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameIdType {
+    App,
+    GameMod,
+    Shortcut,
+    P2P,
+    /// Raw value of a type this crate does not know
+    Unknown(u8),
+}
+
+/// 64-bit GameID of the game a server runs, which may be a mod of a Steam app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameId(u64);
+
+impl GameId {
+    /// Steam app the game is, or is a mod of
+    pub fn app_id(self) -> u32 {
+        (self.0 & 0xFF_FFFF) as u32
+    }
+
+    pub fn kind(self) -> GameIdType {
+        match (self.0 >> 24) as u8 {
+            0 => GameIdType::App,
+            1 => GameIdType::GameMod,
+            2 => GameIdType::Shortcut,
+            3 => GameIdType::P2P,
+            value => GameIdType::Unknown(value),
+        }
+    }
+
+    /// Identifier of the mod, 0 unless the game is a mod
+    pub fn mod_id(self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for GameId {
+    fn from(value: u64) -> Self {
+        GameId(value)
+    }
+}
+
+impl From<GameId> for u64 {
+    fn from(game_id: GameId) -> Self {
+        game_id.0
+    }
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
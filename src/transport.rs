@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::MAX_DATAGRAM_SIZE;
+
+/// Datagram exchange with a single server, used by [`A2S`](crate::A2S) to send
+/// requests and receive responses
+pub trait Transport {
+    /// Open a transport bound to the local address and talking to the server.
+    /// Transports which cannot be opened from an address return an error and
+    /// are given to the client with [`A2S::with_transport`](crate::A2S::with_transport).
+    fn connect(local: SocketAddr, remote: SocketAddr) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Send a datagram to the server
+    fn send(&mut self, datagram: &[u8]) -> io::Result<()>;
+
+    /// Receive a datagram from the server into the buffer, failing with a
+    /// `TimedOut` or `WouldBlock` error if none arrives before the deadline
+    fn recv(&mut self, buf: &mut [u8], deadline: Instant) -> io::Result<usize>;
+
+    /// Drop datagrams left over from previous requests, such as late answers to a
+    /// timed out attempt
+    fn discard_pending(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Address of the server, if the transport knows it
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+}
+
+impl Transport for UdpSocket {
+    fn connect(local: SocketAddr, remote: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        UdpSocket::connect(&socket, remote)?;
+        Ok(socket)
+    }
+
+    fn send(&mut self, datagram: &[u8]) -> io::Result<()> {
+        UdpSocket::send(self, datagram).map(|_| ())
+    }
+
+    fn recv(&mut self, buf: &mut [u8], deadline: Instant) -> io::Result<usize> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(ErrorKind::TimedOut.into());
+        }
+        self.set_read_timeout(Some(remaining))?;
+        UdpSocket::recv(self, buf)
+    }
+
+    fn discard_pending(&mut self) -> io::Result<()> {
+        let mut buf = vec![0u8; MAX_DATAGRAM_SIZE];
+
+        self.set_nonblocking(true)?;
+        let res = loop {
+            match UdpSocket::recv(self, &mut buf) {
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.set_nonblocking(false)?;
+        res
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        UdpSocket::peer_addr(self).ok()
+    }
+}
+
+/// In-memory transport answering each request with scripted datagrams, to test
+/// query flows without networking.
+///
+/// Clones share the same script, so a clone kept by the test can inspect the
+/// requests sent by the client.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedTransport {
+    state: Arc<Mutex<ScriptState>>,
+}
+
+#[derive(Debug, Default)]
+struct ScriptState {
+    /// Datagrams answering each of the next requests
+    replies: VecDeque<Vec<Vec<u8>>>,
+    /// Datagrams answered to the last request and not received yet
+    inbox: VecDeque<Vec<u8>>,
+    /// Every request sent by the client
+    requests: Vec<Vec<u8>>,
+}
+
+impl ScriptedTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next request with the given datagrams, received in that order.
+    /// Datagrams can be repeated or shuffled to test duplicates and reordering.
+    pub fn reply(self, datagrams: &[&[u8]]) -> Self {
+        self.lock()
+            .replies
+            .push_back(datagrams.iter().map(|datagram| datagram.to_vec()).collect());
+        self
+    }
+
+    /// Leave the next request unanswered, as if it was lost
+    pub fn drop_request(self) -> Self {
+        self.reply(&[])
+    }
+
+    /// Requests sent so far, in order
+    pub fn requests(&self) -> Vec<Vec<u8>> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ScriptState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Transport for ScriptedTransport {
+    fn connect(_local: SocketAddr, _remote: SocketAddr) -> io::Result<Self> {
+        Err(ErrorKind::Unsupported.into())
+    }
+
+    /// Record the request and queue its scripted answer. Requests past the end
+    /// of the script are left unanswered.
+    fn send(&mut self, datagram: &[u8]) -> io::Result<()> {
+        let mut state = self.lock();
+        state.requests.push(datagram.to_vec());
+        if let Some(reply) = state.replies.pop_front() {
+            state.inbox.extend(reply);
+        }
+        Ok(())
+    }
+
+    /// Receive the next answered datagram, timing out right away if there is none
+    fn recv(&mut self, buf: &mut [u8], _deadline: Instant) -> io::Result<usize> {
+        let datagram = self.lock().inbox.pop_front().ok_or(ErrorKind::TimedOut)?;
+        let len = datagram.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram[..len]);
+        Ok(len)
+    }
+
+    fn discard_pending(&mut self) -> io::Result<()> {
+        self.lock().inbox.clear();
+        Ok(())
+    }
+}
@@ -1 +1,4 @@
+pub mod random;
+pub mod read_bytes;
 pub mod read_string;
+pub mod write_string;
@@ -0,0 +1,95 @@
+use std::io::Cursor;
+
+use byteorder::{ByteOrder, ReadBytesExt};
+
+use crate::errors::Error;
+
+/// Read numbers, failing with [`Error::Truncated`] when the payload ends too early
+pub trait ReadBytes {
+    fn read_u8(&mut self) -> Result<u8, Error>;
+
+    fn read_u16<B: ByteOrder>(&mut self) -> Result<u16, Error>;
+
+    fn read_i16<B: ByteOrder>(&mut self) -> Result<i16, Error>;
+
+    fn read_u32<B: ByteOrder>(&mut self) -> Result<u32, Error>;
+
+    fn read_i32<B: ByteOrder>(&mut self) -> Result<i32, Error>;
+
+    fn read_f32<B: ByteOrder>(&mut self) -> Result<f32, Error>;
+
+    fn read_u64<B: ByteOrder>(&mut self) -> Result<u64, Error>;
+}
+
+impl<T: AsRef<[u8]>> ReadBytes for Cursor<T> {
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        ensure(self, size_of::<u8>())?;
+        Ok(ReadBytesExt::read_u8(self)?)
+    }
+
+    fn read_u16<B: ByteOrder>(&mut self) -> Result<u16, Error> {
+        ensure(self, size_of::<u16>())?;
+        Ok(ReadBytesExt::read_u16::<B>(self)?)
+    }
+
+    fn read_i16<B: ByteOrder>(&mut self) -> Result<i16, Error> {
+        ensure(self, size_of::<i16>())?;
+        Ok(ReadBytesExt::read_i16::<B>(self)?)
+    }
+
+    fn read_u32<B: ByteOrder>(&mut self) -> Result<u32, Error> {
+        ensure(self, size_of::<u32>())?;
+        Ok(ReadBytesExt::read_u32::<B>(self)?)
+    }
+
+    fn read_i32<B: ByteOrder>(&mut self) -> Result<i32, Error> {
+        ensure(self, size_of::<i32>())?;
+        Ok(ReadBytesExt::read_i32::<B>(self)?)
+    }
+
+    fn read_f32<B: ByteOrder>(&mut self) -> Result<f32, Error> {
+        ensure(self, size_of::<f32>())?;
+        Ok(ReadBytesExt::read_f32::<B>(self)?)
+    }
+
+    fn read_u64<B: ByteOrder>(&mut self) -> Result<u64, Error> {
+        ensure(self, size_of::<u64>())?;
+        Ok(ReadBytesExt::read_u64::<B>(self)?)
+    }
+}
+
+fn ensure<T: AsRef<[u8]>>(cursor: &Cursor<T>, needed: usize) -> Result<(), Error> {
+    let available = cursor
+        .get_ref()
+        .as_ref()
+        .len()
+        .saturating_sub(cursor.position() as usize);
+    if available < needed {
+        return Err(Error::Truncated { needed, available });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::LittleEndian;
+
+    #[test]
+    fn test_read_bytes_truncated() {
+        let mut cursor = Cursor::new(vec![0x01, 0x02, 0x03]);
+        assert_eq!(ReadBytes::read_u8(&mut cursor).unwrap(), 1);
+        assert!(matches!(
+            ReadBytes::read_u32::<LittleEndian>(&mut cursor),
+            Err(Error::Truncated {
+                needed: 4,
+                available: 2
+            })
+        ));
+        assert_eq!(cursor.position(), 1);
+        assert_eq!(
+            ReadBytes::read_u16::<LittleEndian>(&mut cursor).unwrap(),
+            0x0302
+        );
+    }
+}
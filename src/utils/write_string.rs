@@ -0,0 +1,13 @@
+pub trait WriteString {
+    // Write a null terminated string, cut at its first null byte if any
+    fn write_string(&mut self, s: &str);
+}
+
+impl WriteString for Vec<u8> {
+    fn write_string(&mut self, s: &str) {
+        let bytes = s.as_bytes();
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        self.extend_from_slice(&bytes[..end]);
+        self.push(0);
+    }
+}
@@ -0,0 +1,126 @@
+#![cfg(feature = "tokio")]
+
+use std::net::UdpSocket;
+use std::thread;
+
+use a2s_query::info::{ServerEnvironment, ServerType};
+use a2s_query::{AsyncA2S, Quirks};
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+
+#[tokio::test]
+async fn test_async_info_with_challenge() {
+    let response_data = &[
+        0xFF, 0xFF, 0xFF, 0xFF, 0x49, 0x02, 0x67, 0x61, 0x6D, 0x65, 0x32, 0x78, 0x73, 0x2E, 0x63,
+        0x6F, 0x6D, 0x20, 0x43, 0x6F, 0x75, 0x6E, 0x74, 0x65, 0x72, 0x2D, 0x53, 0x74, 0x72, 0x69,
+        0x6B, 0x65, 0x20, 0x53, 0x6F, 0x75, 0x72, 0x63, 0x65, 0x20, 0x23, 0x31, 0x00, 0x64, 0x65,
+        0x5F, 0x64, 0x75, 0x73, 0x74, 0x00, 0x63, 0x73, 0x74, 0x72, 0x69, 0x6B, 0x65, 0x00, 0x43,
+        0x6F, 0x75, 0x6E, 0x74, 0x65, 0x72, 0x2D, 0x53, 0x74, 0x72, 0x69, 0x6B, 0x65, 0x3A, 0x20,
+        0x53, 0x6F, 0x75, 0x72, 0x63, 0x65, 0x00, 0xF0, 0x00, 0x05, 0x10, 0x04, 0x64, 0x6C, 0x00,
+        0x00, 0x31, 0x2E, 0x30, 0x2E, 0x30, 0x2E, 0x32, 0x32, 0x00,
+    ];
+    let (server_addr, server_handle) = spawn_server(vec![
+        (None, vec![CHALLENGE_RESPONSE.to_vec()]),
+        (Some(0x0A), vec![response_data.to_vec()]),
+    ]);
+
+    let a2s = AsyncA2S::new(server_addr);
+
+    let info = a2s.info().await.expect("Failed to get info");
+    assert_eq!(info.name, "game2xs.com Counter-Strike Source #1");
+    assert_eq!(info.map, "de_dust");
+    assert_eq!(info.app_id, 240);
+    assert_eq!(info.server_type, ServerType::Dedicated);
+    assert_eq!(info.environment, ServerEnvironment::Linux);
+
+    server_handle.join().expect("Server thread panicked");
+}
+
+#[tokio::test]
+async fn test_async_rules_multi_packet() {
+    let first_packet = vec![
+        0xFE, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0xE0, 0x04, 0xFF, 0xFF, 0xFF,
+        0xFF, 0x45, 0x02, 0x00, 0x6D, 0x70, 0x5F, 0x74, 0x69,
+    ];
+    let second_packet = vec![
+        0xFE, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x02, 0x01, 0xE0, 0x04, 0x6D, 0x65, 0x6C,
+        0x69, 0x6D, 0x69, 0x74, 0x00, 0x33, 0x30, 0x00, 0x73, 0x76, 0x5F, 0x63, 0x68, 0x65, 0x61,
+        0x74, 0x73, 0x00, 0x30, 0x00,
+    ];
+    let (server_addr, server_handle) = spawn_server(vec![
+        (None, vec![CHALLENGE_RESPONSE.to_vec()]),
+        (Some(0x0A), vec![second_packet, first_packet]),
+    ]);
+
+    let a2s = AsyncA2S::new(server_addr);
+
+    let rules = a2s.rules().await.expect("Failed to get rules");
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].name, "mp_timelimit");
+    assert_eq!(rules[0].value, "30");
+    assert_eq!(rules[1].name, "sv_cheats");
+    assert_eq!(rules[1].value, "0");
+
+    server_handle.join().expect("Server thread panicked");
+}
+
+#[tokio::test]
+async fn test_async_rules_explicit_quirks() {
+    let first_packet = vec![
+        0xFE, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x45,
+        0x01, 0x00, 0x73, 0x76, 0x5F,
+    ];
+    let second_packet = vec![
+        0xFE, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x02, 0x01, 0x63, 0x68, 0x65, 0x61, 0x74,
+        0x73, 0x00, 0x30, 0x00,
+    ];
+    let (server_addr, server_handle) = spawn_server(vec![
+        (None, vec![CHALLENGE_RESPONSE.to_vec()]),
+        (Some(0x0A), vec![first_packet, second_packet]),
+    ]);
+
+    let a2s = AsyncA2S::new(server_addr).with_quirks(Quirks {
+        split_size_field: false,
+    });
+
+    let rules = a2s.rules().await.expect("Failed to get rules");
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].name, "sv_cheats");
+    assert_eq!(rules[0].value, "0");
+
+    server_handle.join().expect("Server thread panicked");
+}
+
+/// Answer each received request with the given datagrams, optionally checking
+/// the first byte of the challenge sent by the client
+fn spawn_server(
+    exchanges: Vec<(Option<u8>, Vec<Vec<u8>>)>,
+) -> (std::net::SocketAddr, thread::JoinHandle<()>) {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let server_handle = thread::spawn(move || {
+        let mut buf = [0u8; 64];
+
+        for (challenge, responses) in exchanges {
+            let (n, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive request");
+            if let Some(challenge) = challenge {
+                assert_eq!(buf[n - 4], challenge);
+            }
+            for response in responses {
+                server_socket
+                    .send_to(&response, client_addr)
+                    .expect("Failed to send response");
+            }
+        }
+    });
+
+    (server_addr, server_handle)
+}
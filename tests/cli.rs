@@ -0,0 +1,66 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use a2s_query::info::Info;
+use a2s_query::players::Player;
+use a2s_query::rules::Rule;
+use a2s_query::server::Responder;
+use a2s_query::{JSON_SCHEMA_VERSION, Versioned};
+use serde::de::DeserializeOwned;
+
+mod common;
+
+#[test]
+fn test_cli_output_loads_back() {
+    let players = vec![Player {
+        index: 0,
+        name: "Killer".to_string(),
+        score: 5,
+        duration: 434.5,
+        the_ship: None,
+    }];
+    let rules = vec![Rule {
+        name: "sv_cheats".to_string(),
+        value: "0".to_string(),
+    }];
+    let address = spawn_responder(
+        Responder::new(common::sin_dm_info())
+            .with_players(players.clone())
+            .with_rules(rules.clone()),
+    );
+
+    let info: Versioned<Info> = run(address, "info");
+    assert_eq!(info.schema_version, JSON_SCHEMA_VERSION);
+    assert_eq!(info.data, common::sin_dm_info());
+
+    let output: Versioned<Vec<Player>> = run(address, "players");
+    assert_eq!(output.data, players);
+
+    let output: Versioned<Vec<Rule>> = run(address, "rules");
+    assert_eq!(output.data, rules);
+}
+
+/// Run the binary against the server and parse what it writes
+fn run<T: DeserializeOwned>(address: SocketAddr, command: &str) -> T {
+    let output = Command::new(env!("CARGO_BIN_EXE_a2s-query"))
+        .args(["--host", "127.0.0.1", "--port", &address.port().to_string()])
+        .args(["--timeout", "1000", command])
+        .output()
+        .expect("Failed to run the binary");
+    assert!(output.status.success(), "{output:?}");
+    serde_json::from_slice(&output.stdout).expect("Failed to parse the output")
+}
+
+/// Answer requests until none is received for a while
+fn spawn_responder(mut responder: Responder) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || while responder.serve_one(&socket).is_ok() {});
+    address
+}
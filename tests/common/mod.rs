@@ -0,0 +1,29 @@
+use a2s_query::info::{Info, ServerEnvironment, ServerType};
+
+/// Info of a SiN DM server, whose encoded response most tests receive
+pub fn sin_dm_info() -> Info {
+    Info {
+        protocol: 47,
+        name: "Sensemann SiN DM".to_string(),
+        map: "paradox".to_string(),
+        folder: "SiN 1".to_string(),
+        game: "SiN 1".to_string(),
+        app_id: 1309,
+        players: 0,
+        max_players: 16,
+        bots: 0,
+        server_type: ServerType::NonDedicated,
+        environment: ServerEnvironment::Windows,
+        password: false,
+        vac: false,
+        the_ship: None,
+        version: "1.0.0.0".to_string(),
+        edf: 0,
+        port: None,
+        steam_id: None,
+        sourcetv_info: None,
+        keywords: None,
+        game_id: None,
+        goldsrc: None,
+    }
+}
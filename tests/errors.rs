@@ -0,0 +1,109 @@
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use a2s_query::errors::Error;
+use a2s_query::protocol::{QueryKind, Stage};
+use a2s_query::{A2S, ScriptedTransport};
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+// First of two Source packets of a rules response of answer ID 7
+const RULES_PACKET_0: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0xE0, 0x04, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x45, 0x01, 0x00, 0x73, 0x76, 0x5F,
+];
+
+#[test]
+fn test_errors_timeout() {
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(ScriptedTransport::new());
+
+    let err = a2s.players().expect_err("Request should time out");
+    assert!(matches!(err.root(), Error::Timeout));
+    assert!(err.is_timeout());
+    let context = err.context().expect("Error should have a context");
+    assert_eq!(context.kind, Some(QueryKind::Players));
+    assert_eq!(context.stage, Stage::Info);
+}
+
+#[test]
+fn test_errors_fragment_missing() {
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[RULES_PACKET_0]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let err = a2s.rules().expect_err("Response should be incomplete");
+    assert!(matches!(
+        err.root(),
+        Error::FragmentMissing {
+            number: 1,
+            total: 2
+        }
+    ));
+    assert!(err.is_timeout());
+    let context = err.context().expect("Error should have a context");
+    assert_eq!(context.kind, Some(QueryKind::Rules));
+    assert_eq!(context.stage, Stage::Request);
+}
+
+#[test]
+fn test_errors_fragment_missing_without_challenge() {
+    // The server answers the challenge request with the response itself
+    let transport = ScriptedTransport::new().reply(&[RULES_PACKET_0]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let err = a2s.rules().expect_err("Response should be incomplete");
+    assert!(matches!(err.root(), Error::FragmentMissing { .. }));
+    assert_eq!(err.context().unwrap().stage, Stage::Request);
+}
+
+#[test]
+fn test_errors_challenge_failed() {
+    let mut transport = ScriptedTransport::new();
+    for _ in 0..6 {
+        transport = transport.reply(&[CHALLENGE_RESPONSE]);
+    }
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let err = a2s.rules().expect_err("Challenges should not end");
+    assert!(matches!(err.root(), Error::ChallengeFailed));
+    assert!(!err.is_timeout());
+}
+
+#[test]
+fn test_errors_truncated() {
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[&[0xFF, 0xFF, 0xFF, 0xFF, 0x45, 0x01]]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let err = a2s.rules().expect_err("Response should be truncated");
+    assert!(matches!(
+        err.root(),
+        Error::Truncated {
+            needed: 2,
+            available: 1
+        }
+    ));
+    assert_eq!(err.context().unwrap().stage, Stage::Parse);
+    assert_eq!(
+        err.to_string(),
+        "Rules query failed at the Parse stage: Truncated response: 2 bytes needed, 1 available"
+    );
+}
+
+#[test]
+fn test_errors_peer_address() {
+    // Reserve a port, then close it so that nothing listens on it
+    let address = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let a2s = A2S::builder(address)
+        .timeout(Duration::from_millis(200))
+        .build();
+
+    let err = a2s.info().expect_err("Nothing should answer");
+    let context = err.context().expect("Error should have a context");
+    assert_eq!(context.kind, Some(QueryKind::Info));
+    assert_eq!(context.peer, Some(address));
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::LazyLock;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use a2s_query::fleet::{FleetQuery, FleetResponse, QueryKind};
+
+mod common;
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+const PLAYERS_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x00, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05, 0x00,
+    0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+];
+const RULES_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x45, 0x01, 0x00, 0x73, 0x76, 0x5F, 0x63, 0x68, 0x65, 0x61, 0x74, 0x73,
+    0x00, 0x30, 0x00,
+];
+
+#[test]
+fn test_fleet_multiple_servers() {
+    // Info then rules, both behind a challenge
+    let first_server = spawn_server(vec![
+        CHALLENGE_RESPONSE,
+        info_response(),
+        CHALLENGE_RESPONSE,
+        RULES_RESPONSE,
+    ]);
+    // Players, fetching info first
+    let second_server = spawn_server(vec![info_response(), CHALLENGE_RESPONSE, PLAYERS_RESPONSE]);
+    // Never answers
+    let silent_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    let silent_server = silent_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let mut fleet = FleetQuery::new().with_timeout(Duration::from_millis(300));
+    fleet.add(first_server, QueryKind::Info).unwrap();
+    fleet.add(first_server, QueryKind::Rules).unwrap();
+    fleet.add(second_server, QueryKind::Players).unwrap();
+    fleet.add(silent_server, QueryKind::Info).unwrap();
+    assert_eq!(fleet.pending(), 4);
+
+    let results: HashMap<_, _> = fleet
+        .by_ref()
+        .map(|res| ((res.address, res.kind), res.result))
+        .collect();
+    assert_eq!(results.len(), 4);
+    assert_eq!(fleet.pending(), 0);
+
+    match &results[&(first_server, QueryKind::Info)] {
+        Ok(FleetResponse::Info(info)) => assert_eq!(info.name, "Sensemann SiN DM"),
+        res => panic!("Unexpected info result: {res:?}"),
+    }
+    match &results[&(first_server, QueryKind::Rules)] {
+        Ok(FleetResponse::Rules(rules)) => assert_eq!(rules[0].name, "sv_cheats"),
+        res => panic!("Unexpected rules result: {res:?}"),
+    }
+    match &results[&(second_server, QueryKind::Players)] {
+        Ok(FleetResponse::Players(players)) => assert_eq!(players[0].name, "Killer"),
+        res => panic!("Unexpected players result: {res:?}"),
+    }
+    match &results[&(silent_server, QueryKind::Info)] {
+        Err(e) => assert!(e.is_timeout()),
+        res => panic!("Unexpected info result: {res:?}"),
+    }
+}
+
+#[test]
+fn test_fleet_ipv4_and_ipv6() {
+    // Skip where the loopback has no IPv6 address
+    let Ok(ipv6_socket) = UdpSocket::bind("[::1]:0") else {
+        return;
+    };
+    let ipv4_server = spawn_server(vec![info_response()]);
+    let ipv6_server = serve(ipv6_socket, vec![Some(info_response())]).0;
+
+    let mut fleet = FleetQuery::new().with_timeout(Duration::from_secs(2));
+    fleet.add(ipv4_server, QueryKind::Info).unwrap();
+    fleet.add(ipv6_server, QueryKind::Info).unwrap();
+
+    let results: HashMap<_, _> = fleet.map(|res| (res.address, res.result)).collect();
+    for server in [ipv4_server, ipv6_server] {
+        match &results[&server] {
+            Ok(FleetResponse::Info(info)) => assert_eq!(info.name, "Sensemann SiN DM"),
+            res => panic!("Unexpected info result: {res:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_fleet_players_after_info() {
+    // The players query uses the app ID of the info received just before
+    let (server, requests) = spawn_recording_server(vec![
+        Some(info_response()),
+        Some(CHALLENGE_RESPONSE),
+        Some(PLAYERS_RESPONSE),
+    ]);
+
+    let mut fleet = FleetQuery::new().with_timeout(Duration::from_secs(2));
+    fleet.add(server, QueryKind::Info).unwrap();
+    fleet.add(server, QueryKind::Players).unwrap();
+
+    let results: Vec<_> = fleet.map(|res| (res.kind, res.result)).collect();
+    assert_eq!(results.len(), 2);
+    assert!(matches!(results[0], (QueryKind::Info, Ok(_))));
+    assert!(matches!(results[1], (QueryKind::Players, Ok(_))));
+
+    let headers: Vec<u8> = requests.try_iter().map(|(_, request)| request[4]).collect();
+    assert_eq!(headers, [0x54, 0x55, 0x55]);
+}
+
+#[test]
+fn test_fleet_retry_backoff() {
+    // The first request is lost
+    let (server, requests) = spawn_recording_server(vec![None, Some(info_response())]);
+
+    let mut fleet = FleetQuery::new()
+        .with_timeout(Duration::from_millis(100))
+        .with_retries(1)
+        .with_backoff(Duration::from_millis(400));
+    fleet.add(server, QueryKind::Info).unwrap();
+
+    let result = fleet.next().expect("Missing fleet result");
+    assert!(matches!(result.result, Ok(FleetResponse::Info(_))));
+
+    let times: Vec<Instant> = requests.try_iter().map(|(time, _)| time).collect();
+    assert_eq!(times.len(), 2);
+    // Timeout, then at least half the backoff because of the jitter
+    assert!(times[1] - times[0] >= Duration::from_millis(300));
+}
+
+/// Info response of the SiN DM server
+fn info_response() -> &'static [u8] {
+    static RESPONSE: LazyLock<Vec<u8>> = LazyLock::new(|| common::sin_dm_info().encode());
+    &RESPONSE
+}
+
+/// Answer each received request with the next response
+fn spawn_server(responses: Vec<&'static [u8]>) -> SocketAddr {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+
+        for response in responses {
+            let (_, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive request");
+            server_socket
+                .send_to(response, client_addr)
+                .expect("Failed to send response");
+        }
+    });
+
+    server_addr
+}
+
+/// Answer each received request with the next response, or ignore it if none,
+/// reporting when each request was received
+fn spawn_recording_server(
+    responses: Vec<Option<&'static [u8]>>,
+) -> (SocketAddr, Receiver<(Instant, Vec<u8>)>) {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    serve(server_socket, responses)
+}
+
+fn serve(
+    server_socket: UdpSocket,
+    responses: Vec<Option<&'static [u8]>>,
+) -> (SocketAddr, Receiver<(Instant, Vec<u8>)>) {
+    server_socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+
+        for response in responses {
+            let (n_received, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive request");
+            let _ = sender.send((Instant::now(), buf[..n_received].to_vec()));
+            if let Some(response) = response {
+                server_socket
+                    .send_to(response, client_addr)
+                    .expect("Failed to send response");
+            }
+        }
+    });
+
+    (server_addr, receiver)
+}
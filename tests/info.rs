@@ -1,9 +1,22 @@
 use std::net::UdpSocket;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use a2s_query::A2S;
-use a2s_query::info::{Info, ServerEnvironment, ServerType, TheShipInfo, TheShipMode};
+use a2s_query::info::SourceTV;
+use a2s_query::info::{
+    GoldSrcInfo, Info, ModInfo, ServerEnvironment, ServerType, TheShipInfo, TheShipMode,
+};
+use a2s_query::steam::{GameId, SteamId};
+use a2s_query::{A2S, ScriptedTransport};
+
+mod common;
+
+const SIN_DM_INFO: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x49, 0x2F, 0x53, 0x65, 0x6E, 0x73, 0x65, 0x6D, 0x61, 0x6E, 0x6E, 0x20,
+    0x53, 0x69, 0x4E, 0x20, 0x44, 0x4D, 0x00, 0x70, 0x61, 0x72, 0x61, 0x64, 0x6F, 0x78, 0x00, 0x53,
+    0x69, 0x4E, 0x20, 0x31, 0x00, 0x53, 0x69, 0x4E, 0x20, 0x31, 0x00, 0x1D, 0x05, 0x00, 0x10, 0x00,
+    0x6C, 0x77, 0x00, 0x00, 0x31, 0x2E, 0x30, 0x2E, 0x30, 0x2E, 0x30, 0x00,
+];
 
 #[test]
 fn test_info_counter_strike_source() {
@@ -38,6 +51,7 @@ fn test_info_counter_strike_source() {
         sourcetv_info: None,
         keywords: None,
         game_id: None,
+        goldsrc: None,
     };
     test_data_info(response_data, &expected_info);
 }
@@ -77,42 +91,447 @@ fn test_info_the_ship() {
         sourcetv_info: None,
         keywords: None,
         game_id: None,
+        goldsrc: None,
     };
     test_data_info(response_data, &expected_info);
 }
 
 #[test]
 fn test_info_sin_dm() {
-    let response_data = &[
-        0xFF, 0xFF, 0xFF, 0xFF, 0x49, 0x2F, 0x53, 0x65, 0x6E, 0x73, 0x65, 0x6D, 0x61, 0x6E, 0x6E,
-        0x20, 0x53, 0x69, 0x4E, 0x20, 0x44, 0x4D, 0x00, 0x70, 0x61, 0x72, 0x61, 0x64, 0x6F, 0x78,
-        0x00, 0x53, 0x69, 0x4E, 0x20, 0x31, 0x00, 0x53, 0x69, 0x4E, 0x20, 0x31, 0x00, 0x1D, 0x05,
-        0x00, 0x10, 0x00, 0x6C, 0x77, 0x00, 0x00, 0x31, 0x2E, 0x30, 0x2E, 0x30, 0x2E, 0x30, 0x00,
-    ];
+    // The info other tests encode is the response of the real server, with an empty EDF
+    assert_eq!(common::sin_dm_info().encode(), [SIN_DM_INFO, &[0]].concat());
+    test_data_info(SIN_DM_INFO, &common::sin_dm_info());
+}
+
+const HLDS_INFO: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x6D, 0x31, 0x32, 0x37, 0x2E, 0x30, 0x2E, 0x30, 0x2E, 0x31, 0x3A, 0x32,
+    0x37, 0x30, 0x31, 0x35, 0x00, 0x48, 0x4C, 0x44, 0x53, 0x00, 0x63, 0x72, 0x6F, 0x73, 0x73, 0x66,
+    0x69, 0x72, 0x65, 0x00, 0x76, 0x61, 0x6C, 0x76, 0x65, 0x00, 0x48, 0x61, 0x6C, 0x66, 0x2D, 0x4C,
+    0x69, 0x66, 0x65, 0x00, 0x02, 0x10, 0x2F, 0x44, 0x4C, 0x00, 0x01, 0x6C, 0x69, 0x6E, 0x6B, 0x00,
+    0x64, 0x6C, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00,
+];
+
+#[test]
+fn test_info_goldsrc_obsolete() {
     let expected_info = Info {
         protocol: 47,
-        name: "Sensemann SiN DM".to_string(),
-        map: "paradox".to_string(),
-        folder: "SiN 1".to_string(),
-        game: "SiN 1".to_string(),
-        app_id: 1309,
-        players: 0,
+        name: "HLDS".to_string(),
+        map: "crossfire".to_string(),
+        folder: "valve".to_string(),
+        game: "Half-Life".to_string(),
+        app_id: 0,
+        players: 2,
         max_players: 16,
         bots: 0,
+        server_type: ServerType::Dedicated,
+        environment: ServerEnvironment::Linux,
+        password: false,
+        vac: true,
+        the_ship: None,
+        version: "".to_string(),
+        edf: 0,
+        port: None,
+        steam_id: None,
+        sourcetv_info: None,
+        keywords: None,
+        game_id: None,
+        goldsrc: Some(GoldSrcInfo {
+            address: "127.0.0.1:27015".to_string(),
+            mod_info: Some(ModInfo {
+                link: "link".to_string(),
+                download_link: "dl".to_string(),
+                version: 1,
+                size: 1024,
+                multiplayer_only: true,
+                own_dll: false,
+            }),
+        }),
+    };
+    assert_eq!(expected_info.encode(), HLDS_INFO);
+    test_data_info(HLDS_INFO, &expected_info);
+}
+
+#[test]
+fn test_info_goldsrc_unknown_server_type_keeps_its_case() {
+    let mut response_data = HLDS_INFO.to_vec();
+    response_data[55] = b'X';
+    response_data[56] = b'Q';
+
+    let info = Info::decode(&response_data).expect("Failed to decode info");
+    assert_eq!(info.server_type, ServerType::Unknown(b'X'));
+    assert_eq!(info.environment, ServerEnvironment::Unknown(b'Q'));
+}
+
+#[test]
+fn test_info_retry_after_dropped_request() {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let server_handle = thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive first info request");
+        let (_, client_addr) = server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive second info request");
+        server_socket
+            .send_to(SIN_DM_INFO, client_addr)
+            .expect("Failed to send info response");
+    });
+
+    let a2s = A2S::builder(server_addr)
+        .timeout(Duration::from_millis(200))
+        .retries(2)
+        .backoff(Duration::from_millis(10))
+        .build();
+
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.name, "Sensemann SiN DM");
+
+    server_handle.join().expect("Server thread panicked");
+}
+
+#[test]
+fn test_info_reuses_socket() {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let server_handle = thread::spawn(move || {
+        let mut buf = [0u8; 25];
+        let mut client_addrs = Vec::new();
+
+        for _ in 0..2 {
+            let (_, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive info request");
+            server_socket
+                .send_to(SIN_DM_INFO, client_addr)
+                .expect("Failed to send info response");
+            client_addrs.push(client_addr);
+        }
+        client_addrs
+    });
+
+    let a2s = A2S::new(server_addr);
+    a2s.info().expect("Failed to get info");
+    a2s.info().expect("Failed to get info");
+
+    let client_addrs = server_handle.join().expect("Server thread panicked");
+    assert_eq!(client_addrs[0], client_addrs[1]);
+}
+
+#[test]
+fn test_info_ipv6() {
+    let Ok(server_socket) = UdpSocket::bind("[::1]:0") else {
+        // IPv6 is not available on this host
+        return;
+    };
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let server_handle = thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        let (_, client_addr) = server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive info request");
+        server_socket
+            .send_to(SIN_DM_INFO, client_addr)
+            .expect("Failed to send info response");
+    });
+
+    let a2s = A2S::new(server_addr);
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.name, "Sensemann SiN DM");
+    assert_eq!(a2s.peer_addr(), Some(server_addr));
+
+    server_handle.join().expect("Server thread panicked");
+}
+
+#[test]
+fn test_info_falls_back_to_next_address() {
+    let silent_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    let silent_addr = silent_socket
+        .local_addr()
+        .expect("Failed to get local address");
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let server_handle = thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        let (_, client_addr) = server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive info request");
+        server_socket
+            .send_to(SIN_DM_INFO, client_addr)
+            .expect("Failed to send info response");
+    });
+
+    let addresses = [silent_addr, server_addr];
+    let a2s = A2S::builder(&addresses[..])
+        .timeout(Duration::from_millis(600))
+        .build();
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.name, "Sensemann SiN DM");
+    assert_eq!(a2s.peer_addr(), Some(server_addr));
+
+    server_handle.join().expect("Server thread panicked");
+}
+
+#[test]
+fn test_info_deadline() {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let a2s = A2S::builder(server_addr)
+        .timeout(Duration::from_secs(1))
+        .retries(10)
+        .deadline(Duration::from_millis(300))
+        .build();
+
+    let start = Instant::now();
+    let err = a2s.info().expect_err("Server should not answer");
+    assert!(err.is_timeout());
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_info_unknown_server_type_and_environment() {
+    let mut response_data = SIN_DM_INFO.to_vec();
+    response_data[48] = 0x00;
+    response_data[49] = b'W';
+    let a2s = A2S::new("127.0.0.1:27015")
+        .with_transport(ScriptedTransport::new().reply(&[&response_data]));
+
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.server_type, ServerType::Unknown(0));
+    assert_eq!(info.environment, ServerEnvironment::Unknown(b'W'));
+
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["server_type"], serde_json::json!({ "Unknown": 0 }));
+    assert_eq!(json["environment"], serde_json::json!({ "Unknown": 87 }));
+}
+
+#[test]
+fn test_info_wide_app_id_and_port() {
+    let mut response_data = SIN_DM_INFO.to_vec();
+    response_data[43..45].copy_from_slice(&0xC648u16.to_le_bytes());
+    response_data.push(0x81);
+    response_data.extend_from_slice(&40000u16.to_le_bytes());
+    response_data.extend_from_slice(&0x23C648u64.to_le_bytes());
+    let a2s = A2S::new("127.0.0.1:27015")
+        .with_transport(ScriptedTransport::new().reply(&[&response_data]));
+
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.app_id, 2344520);
+    assert_eq!(info.port, Some(40000));
+    assert_eq!(info.game_id, Some(GameId::from(2344520)));
+}
+
+#[test]
+fn test_info_app_id_not_matching_game_id() {
+    let mut response_data = SIN_DM_INFO.to_vec();
+    response_data.push(0x01);
+    response_data.extend_from_slice(&0x23C648u64.to_le_bytes());
+    let a2s = A2S::new("127.0.0.1:27015")
+        .with_transport(ScriptedTransport::new().reply(&[&response_data]));
+
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.app_id, 1309);
+}
+
+#[test]
+fn test_info_encode_round_trip() {
+    let info = Info {
+        protocol: 17,
+        name: "Encoded".to_string(),
+        map: "cp_badlands".to_string(),
+        folder: "tf".to_string(),
+        game: "Team Fortress".to_string(),
+        app_id: 1_250_000,
+        players: 20,
+        max_players: 24,
+        bots: 2,
+        server_type: ServerType::Unknown(b'x'),
+        environment: ServerEnvironment::Mac,
+        password: true,
+        vac: true,
+        the_ship: None,
+        version: "8604597".to_string(),
+        edf: 0xF1,
+        port: Some(27015),
+        steam_id: Some(SteamId::from(85568392920039460)),
+        sourcetv_info: Some(SourceTV {
+            port: 27020,
+            name: "SourceTV".to_string(),
+        }),
+        keywords: Some("payload,increased_maxplayers".to_string()),
+        game_id: Some(GameId::from(1_250_000)),
+        goldsrc: None,
+    };
+    assert_eq!(Info::decode(&info.encode()).unwrap(), info);
+
+    let sin_dm = Info::decode(SIN_DM_INFO).unwrap();
+    let mut encoded = SIN_DM_INFO.to_vec();
+    encoded.push(0x00);
+    assert_eq!(sin_dm.encode(), encoded);
+}
+
+#[test]
+fn test_info_encode_the_ship_and_goldsrc_round_trip() {
+    let the_ship = Info {
+        protocol: 7,
+        name: "Ship Server".to_string(),
+        map: "batavier".to_string(),
+        folder: "ship".to_string(),
+        game: "The Ship".to_string(),
+        app_id: 2400,
+        players: 1,
+        max_players: 5,
+        bots: 0,
         server_type: ServerType::NonDedicated,
         environment: ServerEnvironment::Windows,
         password: false,
         vac: false,
+        the_ship: Some(TheShipInfo {
+            mode: TheShipMode::VIPTeam,
+            witnesses: 3,
+            duration: 10,
+        }),
+        version: "1.0.0.4".to_string(),
+        edf: 0,
+        port: None,
+        steam_id: None,
+        sourcetv_info: None,
+        keywords: None,
+        game_id: None,
+        goldsrc: None,
+    };
+    assert_eq!(Info::decode(&the_ship.encode()).unwrap(), the_ship);
+
+    let goldsrc = Info {
+        app_id: 0,
         the_ship: None,
-        version: "1.0.0.0".to_string(),
+        version: String::new(),
+        goldsrc: Some(GoldSrcInfo {
+            address: "127.0.0.1:27015".to_string(),
+            mod_info: Some(ModInfo {
+                link: "link".to_string(),
+                download_link: "dl".to_string(),
+                version: 1,
+                size: 1024,
+                multiplayer_only: true,
+                own_dll: false,
+            }),
+        }),
+        ..the_ship
+    };
+    assert_eq!(Info::decode(&goldsrc.encode()).unwrap(), goldsrc);
+}
+
+#[test]
+fn test_info_encode_fields_the_format_requires() {
+    let info = Info {
+        protocol: 7,
+        name: "Ship Server".to_string(),
+        map: "batavier".to_string(),
+        folder: "ship".to_string(),
+        game: "The Ship".to_string(),
+        app_id: 2400,
+        players: 1,
+        max_players: 5,
+        bots: 0,
+        server_type: ServerType::Dedicated,
+        environment: ServerEnvironment::Linux,
+        password: false,
+        vac: false,
+        the_ship: None,
+        version: "1.0.0.4".to_string(),
         edf: 0,
         port: None,
         steam_id: None,
         sourcetv_info: None,
         keywords: None,
         game_id: None,
+        goldsrc: None,
     };
-    test_data_info(response_data, &expected_info);
+
+    // The Ship info is sent anyway
+    let decoded = Info::decode(&info.encode()).unwrap();
+    assert_eq!(
+        decoded.the_ship,
+        Some(TheShipInfo {
+            mode: TheShipMode::Hunt,
+            witnesses: 0,
+            duration: 0,
+        })
+    );
+    assert_eq!(
+        Info {
+            the_ship: None,
+            ..decoded
+        },
+        info
+    );
+
+    // A wide app ID is sent in a GameID
+    let info = Info {
+        app_id: 1_250_000,
+        ..info
+    };
+    let decoded = Info::decode(&info.encode()).unwrap();
+    assert_eq!(decoded.app_id, 1_250_000);
+    assert_eq!(decoded.game_id, Some(GameId::from(1_250_000)));
+    assert_eq!(decoded.edf, 0x01);
+    assert_eq!(
+        Info {
+            game_id: None,
+            edf: 0,
+            ..decoded
+        },
+        info
+    );
+
+    // The extra data flags follow the fields present
+    let info = Info {
+        port: Some(27015),
+        ..common::sin_dm_info()
+    };
+    assert_eq!(Info::decode(&info.encode()).unwrap().edf, 0x80);
+
+    // The GoldSource format has no app ID, version nor extra data
+    let info = Info {
+        goldsrc: Some(GoldSrcInfo {
+            address: "127.0.0.1:27015".to_string(),
+            mod_info: None,
+        }),
+        ..info
+    };
+    let decoded = Info::decode(&info.encode()).unwrap();
+    assert_eq!(
+        Info {
+            app_id: 1309,
+            version: "1.0.0.0".to_string(),
+            port: Some(27015),
+            ..decoded
+        },
+        info
+    );
 }
 
 fn test_data_info(response_data: &'static [u8], expected_info: &Info) {
@@ -131,7 +550,7 @@ fn test_data_info(response_data: &'static [u8], expected_info: &Info) {
             .recv_from(&mut buf)
             .expect("Failed to receive info request");
         server_socket
-            .send_to(&response_data, client_addr)
+            .send_to(response_data, client_addr)
             .expect("Failed to send info response");
     });
 
@@ -144,3 +563,70 @@ fn test_data_info(response_data: &'static [u8], expected_info: &Info) {
 
     server_handle.join().expect("Server thread panicked");
 }
+
+#[test]
+fn test_info_local_port_range() {
+    let (server_addr, server_handle) = spawn_sin_dm_server();
+
+    // Find a free port for the client, then release it
+    let free_port = UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .expect("Failed to find a free port")
+        .port();
+
+    let a2s = A2S::builder(server_addr)
+        .local_address("127.0.0.1".parse().unwrap())
+        .local_port_range(free_port..=free_port)
+        .timeout(Duration::from_millis(500))
+        .build();
+    a2s.info().expect("Failed to get info");
+
+    let client_addr = server_handle.join().expect("Server thread panicked");
+    assert_eq!(client_addr.port(), free_port);
+}
+
+#[test]
+fn test_info_with_socket() {
+    let (server_addr, server_handle) = spawn_sin_dm_server();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind client socket");
+    let local_addr = socket.local_addr().expect("Failed to get local address");
+
+    let a2s = A2S::builder(server_addr)
+        .timeout(Duration::from_millis(500))
+        .build()
+        .with_socket(socket)
+        .expect("Failed to use socket");
+    a2s.info().expect("Failed to get info");
+
+    let client_addr = server_handle.join().expect("Server thread panicked");
+    assert_eq!(client_addr, local_addr);
+}
+
+/// Answer a single info request, returning the address of the client
+fn spawn_sin_dm_server() -> (
+    std::net::SocketAddr,
+    thread::JoinHandle<std::net::SocketAddr>,
+) {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let server_handle = thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        let (_, client_addr) = server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive info request");
+        server_socket
+            .send_to(SIN_DM_INFO, client_addr)
+            .expect("Failed to send info response");
+        client_addr
+    });
+
+    (server_addr, server_handle)
+}
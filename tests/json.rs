@@ -0,0 +1,124 @@
+use serde_json::json;
+
+use a2s_query::info::{Info, ServerEnvironment, ServerType, SourceTV, TheShipInfo, TheShipMode};
+use a2s_query::players::Player;
+use a2s_query::rules::Rule;
+use a2s_query::steam::{GameId, SteamId};
+
+#[test]
+fn test_json_info_schema() {
+    let info = Info {
+        protocol: 17,
+        name: "Example Server".to_string(),
+        map: "de_dust2".to_string(),
+        folder: "csgo".to_string(),
+        game: "Counter-Strike: Global Offensive".to_string(),
+        app_id: 730,
+        players: 12,
+        max_players: 32,
+        bots: 0,
+        server_type: ServerType::Dedicated,
+        environment: ServerEnvironment::Unknown(b'x'),
+        password: false,
+        vac: true,
+        the_ship: None,
+        version: "2025.03.26".to_string(),
+        edf: 0xF1,
+        port: Some(27015),
+        steam_id: Some(SteamId::from(85568392920039460)),
+        sourcetv_info: Some(SourceTV {
+            port: 27020,
+            name: "SourceTV".to_string(),
+        }),
+        keywords: Some("secure".to_string()),
+        game_id: Some(GameId::from(730)),
+        goldsrc: None,
+    };
+    let expected = json!({
+        "protocol": 17,
+        "name": "Example Server",
+        "map": "de_dust2",
+        "folder": "csgo",
+        "game": "Counter-Strike: Global Offensive",
+        "app_id": 730,
+        "players": 12,
+        "max_players": 32,
+        "bots": 0,
+        "server_type": "Dedicated",
+        "environment": {"Unknown": 120},
+        "password": false,
+        "vac": true,
+        "version": "2025.03.26",
+        "edf": 241,
+        "port": 27015,
+        "steam_id": 85568392920039460u64,
+        "sourcetv_info": {"sourcetv_port": 27020, "sourcetv_name": "SourceTV"},
+        "keywords": "secure",
+        "game_id": 730,
+    });
+
+    assert_eq!(serde_json::to_value(&info).unwrap(), expected);
+    assert_eq!(serde_json::from_value::<Info>(expected).unwrap(), info);
+}
+
+#[test]
+fn test_json_info_the_ship_round_trip() {
+    let info = Info {
+        protocol: 7,
+        name: "Ship Server".to_string(),
+        map: "batavier".to_string(),
+        folder: "ship".to_string(),
+        game: "The Ship".to_string(),
+        app_id: 2400,
+        players: 1,
+        max_players: 5,
+        bots: 0,
+        server_type: ServerType::NonDedicated,
+        environment: ServerEnvironment::Windows,
+        password: false,
+        vac: false,
+        the_ship: Some(TheShipInfo {
+            mode: TheShipMode::Elimination,
+            witnesses: 3,
+            duration: 3,
+        }),
+        version: "1.0.0.4".to_string(),
+        edf: 0,
+        port: None,
+        steam_id: None,
+        sourcetv_info: None,
+        keywords: None,
+        game_id: None,
+        goldsrc: None,
+    };
+
+    let value = serde_json::to_value(&info).unwrap();
+    assert_eq!(value["mode"], "Elimination");
+    assert_eq!(value["witnesses"], 3);
+    assert_eq!(serde_json::from_value::<Info>(value).unwrap(), info);
+}
+
+#[test]
+fn test_json_players_and_rules_round_trip() {
+    let players = vec![Player {
+        index: 0,
+        name: "Killer".to_string(),
+        score: 5,
+        duration: 434.28445,
+        the_ship: None,
+    }];
+    let json = serde_json::to_string(&players).unwrap();
+    assert_eq!(
+        json,
+        r#"[{"index":0,"name":"Killer","score":5,"duration":434.28445}]"#
+    );
+    assert_eq!(serde_json::from_str::<Vec<Player>>(&json).unwrap(), players);
+
+    let rules = vec![Rule {
+        name: "sv_cheats".to_string(),
+        value: "0".to_string(),
+    }];
+    let json = serde_json::to_string(&rules).unwrap();
+    assert_eq!(json, r#"[{"name":"sv_cheats","value":"0"}]"#);
+    assert_eq!(serde_json::from_str::<Vec<Rule>>(&json).unwrap(), rules);
+}
@@ -0,0 +1,173 @@
+use a2s_query::errors::Error;
+use a2s_query::info::{Info, ServerType};
+use a2s_query::lenient::Warning;
+use a2s_query::players::Player;
+use a2s_query::rules::Rule;
+use a2s_query::{A2S, ScriptedTransport};
+
+mod common;
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+// Two players announced, a single one sent
+const MISSING_PLAYER_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x02, 0x00, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05, 0x00,
+    0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+];
+// Three rules announced, cut off after the name of the second one
+const TRUNCATED_RULES_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x45, 0x03, 0x00, 0x73, 0x76, 0x5F, 0x63, 0x68, 0x65, 0x61, 0x74, 0x73,
+    0x00, 0x30, 0x00, 0x6D, 0x70, 0x00,
+];
+
+/// Info of a server whose type byte is 0
+fn unknown_type_info_response() -> Vec<u8> {
+    Info {
+        server_type: ServerType::Unknown(0),
+        ..common::sin_dm_info()
+    }
+    .encode()
+}
+
+/// Info announcing a port in its EDF, cut off in the middle of it
+fn truncated_port_info_response() -> Vec<u8> {
+    let mut response = Info {
+        port: Some(27015),
+        ..common::sin_dm_info()
+    }
+    .encode();
+    response.pop();
+    response
+}
+
+#[test]
+fn test_lenient_unknown_server_type() {
+    let strict = A2S::new("127.0.0.1:27015")
+        .with_transport(ScriptedTransport::new().reply(&[&unknown_type_info_response()]));
+    let outcome = strict.info_outcome().expect("Failed to get info");
+    assert_eq!(outcome.value.server_type, ServerType::Unknown(0));
+    assert!(outcome.warnings.is_empty());
+
+    let lenient = A2S::new("127.0.0.1:27015")
+        .with_lenient(true)
+        .with_transport(ScriptedTransport::new().reply(&[&unknown_type_info_response()]));
+    let outcome = lenient.info_outcome().expect("Failed to get info");
+    assert_eq!(outcome.value.name, "Sensemann SiN DM");
+    assert_eq!(outcome.value.server_type, ServerType::Unknown(0));
+    assert_eq!(outcome.value.version, "1.0.0.0");
+    assert_eq!(outcome.warnings, [Warning::UnknownServerType(0)]);
+}
+
+#[test]
+fn test_lenient_truncated_info() {
+    let strict = A2S::new("127.0.0.1:27015")
+        .with_transport(ScriptedTransport::new().reply(&[&truncated_port_info_response()]));
+    let err = strict.info().expect_err("Truncated info should fail");
+    assert!(matches!(
+        err.root(),
+        Error::Truncated {
+            needed: 2,
+            available: 1
+        }
+    ));
+
+    let lenient = A2S::builder("127.0.0.1:27015")
+        .lenient(true)
+        .build()
+        .with_transport(ScriptedTransport::new().reply(&[&truncated_port_info_response()]));
+    let outcome = lenient.info_outcome().expect("Failed to get info");
+    assert_eq!(outcome.value.app_id, 1309);
+    assert_eq!(outcome.value.edf, 0x80);
+    assert_eq!(outcome.value.port, None);
+    assert_eq!(outcome.warnings, [Warning::Truncated { field: "port" }]);
+}
+
+#[test]
+fn test_lenient_missing_player() {
+    let transport = ScriptedTransport::new()
+        .reply(&[&unknown_type_info_response()])
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[MISSING_PLAYER_RESPONSE]);
+    let a2s = A2S::new("127.0.0.1:27015")
+        .with_lenient(true)
+        .with_transport(transport);
+
+    // Warnings of the info fetched first are kept
+    let outcome = a2s.players_outcome().expect("Failed to get players");
+    assert_eq!(outcome.value.len(), 1);
+    assert_eq!(outcome.value[0].name, "Killer");
+    assert_eq!(
+        outcome.warnings,
+        [
+            Warning::UnknownServerType(0),
+            Warning::CountMismatch {
+                expected: 2,
+                found: 1
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_lenient_truncated_rules() {
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[TRUNCATED_RULES_RESPONSE]);
+    let a2s = A2S::new("127.0.0.1:27015")
+        .with_lenient(true)
+        .with_transport(transport);
+
+    let outcome = a2s.rules_outcome().expect("Failed to get rules");
+    assert_eq!(outcome.value.len(), 1);
+    assert_eq!(outcome.value[0].name, "sv_cheats");
+    assert_eq!(outcome.value[0].value, "0");
+    assert_eq!(
+        outcome.warnings,
+        [
+            Warning::Truncated { field: "rules" },
+            Warning::CountMismatch {
+                expected: 3,
+                found: 1
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_lenient_info_truncated_after_strings() {
+    // Cut off after the app ID
+    let response = &common::sin_dm_info().encode()[..45];
+    let err = Info::decode(response).expect_err("Truncated info should fail");
+    assert!(matches!(err, Error::Truncated { .. }));
+
+    let (info, warnings) = Info::decode_lenient(response).expect("Failed to decode info");
+    assert_eq!(info.name, "Sensemann SiN DM");
+    assert_eq!(info.app_id, 1309);
+    assert_eq!(info.max_players, 0);
+    assert_eq!(info.server_type, ServerType::Unknown(0));
+    assert_eq!(info.version, "");
+    assert_eq!(warnings, [Warning::Truncated { field: "players" }]);
+}
+
+#[test]
+fn test_lenient_decode() {
+    let (info, warnings) =
+        Info::decode_lenient(&truncated_port_info_response()).expect("Failed to decode info");
+    assert_eq!(info.port, None);
+    assert_eq!(warnings, [Warning::Truncated { field: "port" }]);
+
+    let (players, warnings) = Player::decode_list_lenient(MISSING_PLAYER_RESPONSE, false)
+        .expect("Failed to decode players");
+    assert_eq!(players.len(), 1);
+    assert_eq!(
+        warnings,
+        [Warning::CountMismatch {
+            expected: 2,
+            found: 1
+        }]
+    );
+
+    let (rules, warnings) =
+        Rule::decode_list_lenient(TRUNCATED_RULES_RESPONSE).expect("Failed to decode rules");
+    assert_eq!(rules.len(), 1);
+    assert_eq!(warnings.len(), 2);
+}
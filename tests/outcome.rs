@@ -0,0 +1,77 @@
+use a2s_query::{A2S, ScriptedTransport};
+
+mod common;
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+const PLAYERS_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x00, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05, 0x00,
+    0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+];
+// Rules split in two Source packets of answer ID 7
+const RULES_PACKET_0: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0xE0, 0x04, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x45, 0x01, 0x00, 0x73, 0x76, 0x5F,
+];
+const RULES_PACKET_1: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x01, 0xE0, 0x04, 0x63, 0x68, 0x65, 0x61,
+    0x74, 0x73, 0x00, 0x30, 0x00,
+];
+
+#[test]
+fn test_outcome_info() {
+    let info = common::sin_dm_info().encode();
+    let transport = ScriptedTransport::new().reply(&[&info]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let outcome = a2s.info_outcome().expect("Failed to get info");
+    assert_eq!(outcome.value.name, "Sensemann SiN DM");
+    assert!(!outcome.challenged);
+    assert!(!outcome.split);
+    assert!(!outcome.compressed);
+    assert_eq!(outcome.datagrams, 1);
+    assert_eq!(outcome.bytes, info.len());
+    assert_eq!(outcome.peer, None);
+}
+
+#[test]
+fn test_outcome_players_challenge() {
+    let info = common::sin_dm_info().encode();
+    let transport = ScriptedTransport::new()
+        .reply(&[&info])
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[PLAYERS_RESPONSE]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let outcome = a2s.players_outcome().expect("Failed to get players");
+    assert_eq!(outcome.value[0].name, "Killer");
+    assert!(outcome.challenged);
+    assert!(!outcome.split);
+    assert_eq!(outcome.datagrams, 3);
+    assert_eq!(
+        outcome.bytes,
+        info.len() + CHALLENGE_RESPONSE.len() + PLAYERS_RESPONSE.len()
+    );
+}
+
+#[test]
+fn test_outcome_rules_split() {
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[RULES_PACKET_0, RULES_PACKET_1]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let outcome = a2s.rules_outcome().expect("Failed to get rules");
+    assert_eq!(outcome.value[0].name, "sv_cheats");
+    assert!(outcome.challenged);
+    assert!(outcome.split);
+    assert!(!outcome.compressed);
+    assert_eq!(outcome.datagrams, 3);
+    assert_eq!(
+        outcome.bytes,
+        CHALLENGE_RESPONSE.len() + RULES_PACKET_0.len() + RULES_PACKET_1.len()
+    );
+
+    let rule_count = outcome.map(|rules| rules.len());
+    assert_eq!(rule_count.value, 1);
+    assert!(rule_count.split);
+}
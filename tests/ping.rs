@@ -0,0 +1,155 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use a2s_query::A2S;
+use a2s_query::ping::PingMethod;
+use a2s_query::transport::Transport;
+
+const PING_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x6A, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30,
+    0x30, 0x30, 0x30, 0x00,
+];
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+
+#[test]
+fn test_ping_a2a_ping() {
+    let a2s = A2S::new(spawn_server(1, false));
+
+    let rtt = a2s.ping_with(PingMethod::A2APing).expect("Failed to ping");
+    assert!(rtt < Duration::from_secs(1));
+}
+
+#[test]
+fn test_ping_challenge() {
+    let a2s = A2S::new(spawn_server(1, true));
+
+    let rtt = a2s
+        .ping_with(PingMethod::Challenge)
+        .expect("Failed to ping");
+    assert!(rtt < Duration::from_secs(1));
+}
+
+#[test]
+fn test_ping_series() {
+    let a2s = A2S::new(spawn_server(4, false));
+
+    let stats = a2s.ping_series(4).expect("Failed to ping");
+    assert_eq!(stats.sent, 4);
+    assert_eq!(stats.received, 4);
+    assert_eq!(stats.loss(), 0.0);
+    assert!(stats.min <= stats.avg && stats.avg <= stats.max);
+    assert!(stats.jitter <= stats.max - stats.min);
+}
+
+#[test]
+fn test_ping_excludes_time_before_sending() {
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(SlowTransport);
+
+    let rtt = a2s.ping_with(PingMethod::A2APing).expect("Failed to ping");
+    assert!(rtt < Duration::from_millis(200));
+}
+
+#[test]
+fn test_ping_detects_challenge_quickly() {
+    let a2s = A2S::builder(spawn_challenge_only_server(3))
+        .timeout(Duration::from_secs(2))
+        .build();
+
+    let start = Instant::now();
+    a2s.ping().expect("Failed to ping");
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(a2s.ping_method(), Some(PingMethod::Challenge));
+
+    let start = Instant::now();
+    let stats = a2s.ping_series(2).expect("Failed to ping");
+    assert_eq!(stats.received, 2);
+    assert!(start.elapsed() < Duration::from_millis(250));
+}
+
+/// Answer `count` challenge requests, ignoring A2A_PING requests
+fn spawn_challenge_only_server(count: usize) -> std::net::SocketAddr {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 25];
+        let mut answered = 0;
+
+        while answered < count {
+            let Ok((_, client_addr)) = server_socket.recv_from(&mut buf) else {
+                return;
+            };
+            if buf[4] == 0x55 {
+                server_socket
+                    .send_to(CHALLENGE_RESPONSE, client_addr)
+                    .expect("Failed to send challenge");
+                answered += 1;
+            }
+        }
+    });
+
+    server_addr
+}
+
+/// Answer `count` requests with an A2A_PING response, or a challenge if requested
+fn spawn_server(count: usize, challenge: bool) -> std::net::SocketAddr {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        for _ in 0..count {
+            let (_, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive ping request");
+            let response = if challenge {
+                CHALLENGE_RESPONSE
+            } else {
+                PING_RESPONSE
+            };
+            server_socket
+                .send_to(response, client_addr)
+                .expect("Failed to send ping response");
+        }
+    });
+
+    thread::sleep(Duration::from_millis(10));
+
+    server_addr
+}
+
+/// Transport answering pings right away, but slow to get ready to send them
+struct SlowTransport;
+
+impl Transport for SlowTransport {
+    fn connect(_local: SocketAddr, _remote: SocketAddr) -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn send(&mut self, _datagram: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8], _deadline: Instant) -> io::Result<usize> {
+        buf[..PING_RESPONSE.len()].copy_from_slice(PING_RESPONSE);
+        Ok(PING_RESPONSE.len())
+    }
+
+    fn discard_pending(&mut self) -> io::Result<()> {
+        thread::sleep(Duration::from_millis(300));
+        Ok(())
+    }
+}
@@ -148,6 +148,124 @@ fn test_players_the_ship() {
     test_data_players(test_info, test_data, &expected_players);
 }
 
+#[test]
+fn test_players_with_info_skips_info_request() {
+    let test_data = &[
+        0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x02, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x20, 0x21,
+        0x21, 0x21, 0x00, 0x05, 0x00, 0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+    ];
+    let expected_players = vec![Player {
+        index: 2,
+        name: "Killer !!!".to_string(),
+        score: 5,
+        duration: 434.28445,
+        the_ship: None,
+    }];
+    let server_addr = spawn_server(vec![test_data]);
+
+    let a2s = A2S::new(server_addr);
+    let players = a2s.players_for_app_id(240).expect("Failed to get players");
+    assert_eq!(players, expected_players);
+}
+
+#[test]
+fn test_players_reuses_cached_app_id() {
+    let test_info = &[
+        0xFF, 0xFF, 0xFF, 0xFF, 0x49, 0x07, 0x53, 0x68, 0x69, 0x70, 0x20, 0x53, 0x65, 0x72, 0x76,
+        0x65, 0x72, 0x00, 0x62, 0x61, 0x74, 0x61, 0x76, 0x69, 0x65, 0x72, 0x00, 0x73, 0x68, 0x69,
+        0x70, 0x00, 0x54, 0x68, 0x65, 0x20, 0x53, 0x68, 0x69, 0x70, 0x00, 0x60, 0x09, 0x01, 0x05,
+        0x00, 0x6C, 0x77, 0x00, 0x00, 0x01, 0x03, 0x03, 0x31, 0x2E, 0x30, 0x2E, 0x30, 0x2E, 0x34,
+        0x00,
+    ];
+    let test_data = &[
+        0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x00, 0x53, 0x68, 0x69, 0x70, 0x6D, 0x61, 0x74, 0x65,
+        0x31, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0xBF, 0x01, 0x00, 0x00, 0x00, 0xC4,
+        0x09, 0x00, 0x00,
+    ];
+    let expected_players = vec![Player {
+        index: 0,
+        name: "Shipmate1".to_string(),
+        score: 0,
+        duration: -1.0,
+        the_ship: Some(TheShipInfo {
+            deaths: 1,
+            money: 2500,
+        }),
+    }];
+    let server_addr = spawn_server(vec![test_info, test_data]);
+
+    let a2s = A2S::new(server_addr);
+    assert_eq!(a2s.app_id(), None);
+    a2s.info().expect("Failed to get info");
+    assert_eq!(a2s.app_id(), Some(2400));
+
+    let players = a2s.players().expect("Failed to get players");
+    assert_eq!(players, expected_players);
+}
+
+/// Answer each received request with the next response
+fn spawn_server(responses: Vec<&'static [u8]>) -> std::net::SocketAddr {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        for response in responses {
+            let (_, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive request");
+            server_socket
+                .send_to(response, client_addr)
+                .expect("Failed to send response");
+        }
+    });
+
+    server_addr
+}
+
+#[test]
+fn test_players_encode_round_trip() {
+    let players = vec![
+        Player {
+            index: 0,
+            name: "Shipmate1".to_string(),
+            score: -3,
+            duration: 3720.9265,
+            the_ship: Some(TheShipInfo {
+                deaths: 4,
+                money: 2500,
+            }),
+        },
+        Player {
+            index: 1,
+            name: "".to_string(),
+            score: 0,
+            duration: -1.0,
+            the_ship: Some(TheShipInfo {
+                deaths: 0,
+                money: 0,
+            }),
+        },
+    ];
+    assert_eq!(
+        Player::decode_list(&Player::encode_list(&players), true).unwrap(),
+        players
+    );
+
+    let response_data = &[
+        0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x02, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05,
+        0x00, 0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+    ];
+    let decoded = Player::decode_list(response_data, false).unwrap();
+    assert_eq!(Player::encode_list(&decoded), response_data);
+}
+
 fn test_data_players(
     info: &'static [u8],
     response_data: &'static [u8],
@@ -168,14 +286,14 @@ fn test_data_players(
             .recv_from(&mut buf)
             .expect("Failed to receive info request");
         server_socket
-            .send_to(&info, client_addr)
+            .send_to(info, client_addr)
             .expect("Failed to send info response");
 
         let (_, client_addr) = server_socket
             .recv_from(&mut buf)
             .expect("Failed to receive players request");
         server_socket
-            .send_to(&response_data, client_addr)
+            .send_to(response_data, client_addr)
             .expect("Failed to send player response");
     });
 
@@ -0,0 +1,146 @@
+use a2s_query::Engine;
+use a2s_query::errors::Error;
+use a2s_query::protocol::{Output, Query, QueryKind, Response};
+
+mod common;
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+const PLAYERS_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x00, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05, 0x00,
+    0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+];
+// Rules split in two Source packets of answer ID 7
+const RULES_PACKET_0: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0xE0, 0x04, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x45, 0x01, 0x00, 0x73, 0x76, 0x5F,
+];
+const RULES_PACKET_1: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x01, 0xE0, 0x04, 0x63, 0x68, 0x65, 0x61,
+    0x74, 0x73, 0x00, 0x30, 0x00,
+];
+
+#[test]
+fn test_protocol_players_fetch_info_then_challenge() {
+    let mut query = Query::new(QueryKind::Players, Engine::Source);
+    assert_eq!(query.request()[4], 0x54);
+
+    match query
+        .handle(&common::sin_dm_info().encode())
+        .expect("Failed to handle info")
+    {
+        Some(Output::Send(request)) => {
+            assert_eq!(
+                request,
+                [0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0xFF, 0xFF, 0xFF, 0xFF]
+            )
+        }
+        output => panic!("Unexpected output: {output:?}"),
+    }
+    assert_eq!(query.app_id(), Some(1309));
+
+    match query
+        .handle(CHALLENGE_RESPONSE)
+        .expect("Failed to handle challenge")
+    {
+        Some(Output::Send(request)) => {
+            assert_eq!(
+                request,
+                [0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0x0A, 0x08, 0x5E, 0xEA]
+            )
+        }
+        output => panic!("Unexpected output: {output:?}"),
+    }
+    assert_eq!(
+        query.challenge(),
+        Some(i32::from_le_bytes([0x0A, 0x08, 0x5E, 0xEA]))
+    );
+    // Retrying resends the request answering the challenge
+    assert_eq!(
+        query.request(),
+        [0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0x0A, 0x08, 0x5E, 0xEA]
+    );
+
+    match query
+        .handle(PLAYERS_RESPONSE)
+        .expect("Failed to handle players")
+    {
+        Some(Output::Done(Response::Players(players))) => assert_eq!(players[0].name, "Killer"),
+        output => panic!("Unexpected output: {output:?}"),
+    }
+}
+
+#[test]
+fn test_protocol_players_with_app_id() {
+    let mut query = Query::new(QueryKind::Players, Engine::Source).with_app_id(1309);
+    assert_eq!(
+        query.request(),
+        [0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0xFF, 0xFF, 0xFF, 0xFF]
+    );
+
+    match query
+        .handle(PLAYERS_RESPONSE)
+        .expect("Failed to handle players")
+    {
+        Some(Output::Done(Response::Players(players))) => assert_eq!(players.len(), 1),
+        output => panic!("Unexpected output: {output:?}"),
+    }
+}
+
+#[test]
+fn test_protocol_split_rules() {
+    let mut query = Query::new(QueryKind::Rules, Engine::Source);
+    query.request();
+
+    assert!(
+        query
+            .handle(RULES_PACKET_1)
+            .expect("Failed to handle packet")
+            .is_none()
+    );
+    match query
+        .handle(RULES_PACKET_0)
+        .expect("Failed to handle packet")
+    {
+        Some(Output::Done(Response::Rules(rules))) => assert_eq!(rules[0].name, "sv_cheats"),
+        output => panic!("Unexpected output: {output:?}"),
+    }
+}
+
+#[test]
+fn test_protocol_retry_discards_partial_response() {
+    let mut query = Query::new(QueryKind::Rules, Engine::Source);
+    query.request();
+    assert!(
+        query
+            .handle(RULES_PACKET_0)
+            .expect("Failed to handle packet")
+            .is_none()
+    );
+
+    // After a timeout, the answer to the resent request starts over
+    query.request();
+    assert!(
+        query
+            .handle(RULES_PACKET_1)
+            .expect("Failed to handle packet")
+            .is_none()
+    );
+}
+
+#[test]
+fn test_protocol_timeout_error() {
+    let mut query = Query::new(QueryKind::Rules, Engine::Source);
+    query.request();
+    assert!(matches!(query.timeout_error(), Error::Timeout));
+
+    query
+        .handle(RULES_PACKET_0)
+        .expect("Failed to handle packet");
+    assert!(matches!(
+        query.timeout_error(),
+        Error::FragmentMissing {
+            number: 1,
+            total: 2
+        }
+    ));
+}
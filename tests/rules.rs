@@ -0,0 +1,266 @@
+use std::io::Read;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use bzip2::Compression;
+use bzip2::read::BzEncoder;
+
+use a2s_query::errors::Error;
+use a2s_query::rules::Rule;
+use a2s_query::{A2S, Engine};
+
+const RULES_PAYLOAD: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x45, 0x02, 0x00, 0x6D, 0x70, 0x5F, 0x74, 0x69, 0x6D, 0x65, 0x6C, 0x69,
+    0x6D, 0x69, 0x74, 0x00, 0x33, 0x30, 0x00, 0x73, 0x76, 0x5F, 0x63, 0x68, 0x65, 0x61, 0x74, 0x73,
+    0x00, 0x30, 0x00,
+];
+
+#[test]
+fn test_rules_single_packet() {
+    let a2s = A2S::new(spawn_server(vec![RULES_PAYLOAD.to_vec()]));
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].name, "mp_timelimit");
+    assert_eq!(rules[0].value, "30");
+    assert_eq!(rules[1].name, "sv_cheats");
+    assert_eq!(rules[1].value, "0");
+}
+
+#[test]
+fn test_rules_encode_round_trip() {
+    let rules = Rule::decode_list(RULES_PAYLOAD).unwrap();
+    assert_eq!(Rule::encode_list(&rules), RULES_PAYLOAD);
+
+    let rules = vec![Rule {
+        name: "sv_tags".to_string(),
+        value: "".to_string(),
+    }];
+    let decoded = Rule::decode_list(&Rule::encode_list(&rules)).unwrap();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].name, "sv_tags");
+    assert_eq!(decoded[0].value, "");
+}
+
+#[test]
+fn test_rules_compressed_multi_packet() {
+    let packets = compressed_packets(
+        RULES_PAYLOAD,
+        RULES_PAYLOAD.len() as u32,
+        crc32fast::hash(RULES_PAYLOAD),
+    );
+    let a2s = A2S::new(spawn_server(packets));
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].name, "mp_timelimit");
+    assert_eq!(rules[1].name, "sv_cheats");
+}
+
+#[test]
+fn test_rules_compressed_checksum_mismatch() {
+    let packets = compressed_packets(
+        RULES_PAYLOAD,
+        RULES_PAYLOAD.len() as u32,
+        crc32fast::hash(RULES_PAYLOAD) ^ 1,
+    );
+    let a2s = A2S::new(spawn_server(packets));
+
+    let err = a2s.rules().expect_err("Checksum should not match");
+    assert!(matches!(err.root(), Error::ChecksumMismatch));
+}
+
+#[test]
+fn test_rules_compressed_larger_than_announced() {
+    // Inflates to megabytes while announcing the size of a small response
+    let mut payload = RULES_PAYLOAD.to_vec();
+    payload.resize(4 << 20, 0);
+    let packets = compressed_packets(
+        &payload,
+        RULES_PAYLOAD.len() as u32,
+        crc32fast::hash(RULES_PAYLOAD),
+    );
+    let a2s = A2S::new(spawn_server(packets));
+
+    let err = a2s
+        .rules()
+        .expect_err("Oversized payload should be rejected");
+    assert!(matches!(err.root(), Error::ChecksumMismatch));
+}
+
+#[test]
+fn test_rules_compressed_announced_size_too_large() {
+    // Both the announced size and the inflated payload exceed the ceiling
+    let mut payload = RULES_PAYLOAD.to_vec();
+    payload.resize(4 << 20, 0);
+    let packets = compressed_packets(&payload, u32::MAX, crc32fast::hash(&payload));
+    let a2s = A2S::new(spawn_server(packets));
+
+    let err = a2s
+        .rules()
+        .expect_err("Oversized announced size should be rejected");
+    assert!(matches!(
+        err.root(),
+        Error::ResponseTooLarge {
+            size: u32::MAX,
+            max: 1_048_576,
+        }
+    ));
+}
+
+#[test]
+fn test_rules_goldsource_multi_packet() {
+    let (first, second) = RULES_PAYLOAD.split_at(20);
+    let mut packets = Vec::new();
+    for (packet_info, payload) in [(0x12, second), (0x02, first)] {
+        let mut packet = vec![0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, packet_info];
+        packet.extend_from_slice(payload);
+        packets.push(packet);
+    }
+    let a2s = A2S::new(spawn_server(packets)).with_engine(Engine::GoldSource);
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].name, "mp_timelimit");
+    assert_eq!(rules[0].value, "30");
+    assert_eq!(rules[1].name, "sv_cheats");
+    assert_eq!(rules[1].value, "0");
+}
+
+#[test]
+fn test_rules_duplicate_and_stray_packets() {
+    let packets = split_packets(RULES_PAYLOAD, 7, 3);
+    let stray = split_packets(RULES_PAYLOAD, 8, 3);
+    let a2s = A2S::new(spawn_server(vec![
+        packets[0].clone(),
+        stray[1].clone(),
+        vec![0xFF, 0xFF, 0xFF],
+        packets[0].clone(),
+        packets[2][..9].to_vec(),
+        packets[2].clone(),
+        packets[1].clone(),
+    ]));
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].name, "mp_timelimit");
+    assert_eq!(rules[1].name, "sv_cheats");
+}
+
+#[test]
+fn test_rules_too_many_packets() {
+    let mut packet = vec![
+        0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0xC8, 0x00, 0xE0, 0x04,
+    ];
+    packet.extend_from_slice(RULES_PAYLOAD);
+    let a2s = A2S::new(spawn_server(vec![packet]));
+
+    let err = a2s
+        .rules()
+        .expect_err("Response should have too many packets");
+    assert!(matches!(
+        err.root(),
+        Error::TooManyPackets { total: 200, .. }
+    ));
+}
+
+#[test]
+fn test_rules_packet_larger_than_announced() {
+    let mut packets = split_packets(RULES_PAYLOAD, 7, 2);
+    // Announce 8 bytes per packet, less than the first packet carries
+    packets[0][10..12].copy_from_slice(&8i16.to_le_bytes());
+    let a2s = A2S::new(spawn_server(packets));
+
+    let err = a2s.rules().expect_err("Packet should be rejected");
+    assert!(matches!(err.root(), Error::InvalidResponse));
+}
+
+#[test]
+fn test_rules_large_datagram() {
+    let mut payload = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x45];
+    payload.extend_from_slice(&200u16.to_le_bytes());
+    for i in 0..200 {
+        payload.extend_from_slice(format!("rule_number_{i}\0value_{i}\0").as_bytes());
+    }
+    assert!(payload.len() > 1400);
+    let a2s = A2S::new(spawn_server(vec![payload]));
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules.len(), 200);
+    assert_eq!(rules[199].name, "rule_number_199");
+    assert_eq!(rules[199].value, "value_199");
+}
+
+/// Split the payload in uncompressed Source packets of the given answer ID
+fn split_packets(payload: &[u8], answer_id: i32, count: u8) -> Vec<Vec<u8>> {
+    let chunk_size = payload.len().div_ceil(count as usize);
+    payload
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(number, chunk)| {
+            let mut packet = Vec::new();
+            packet.extend_from_slice(&(-2i32).to_le_bytes());
+            packet.extend_from_slice(&answer_id.to_le_bytes());
+            packet.extend_from_slice(&[count, number as u8]);
+            packet.extend_from_slice(&(chunk_size as i16).to_le_bytes());
+            packet.extend_from_slice(chunk);
+            packet
+        })
+        .collect()
+}
+
+/// Compress the payload and split it in two packets, the first one carrying
+/// the given decompressed size and checksum
+fn compressed_packets(payload: &[u8], decompressed_size: u32, crc32: u32) -> Vec<Vec<u8>> {
+    let mut compressed = Vec::new();
+    BzEncoder::new(payload, Compression::best())
+        .read_to_end(&mut compressed)
+        .expect("Failed to compress payload");
+
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+    let answer_id: u32 = 0x8000_0001;
+
+    let mut first_packet = Vec::new();
+    first_packet.extend_from_slice(&(-2i32).to_le_bytes());
+    first_packet.extend_from_slice(&answer_id.to_le_bytes());
+    first_packet.extend_from_slice(&[0x02, 0x00, 0xE0, 0x04]);
+    first_packet.extend_from_slice(&decompressed_size.to_le_bytes());
+    first_packet.extend_from_slice(&crc32.to_le_bytes());
+    first_packet.extend_from_slice(first);
+
+    let mut second_packet = Vec::new();
+    second_packet.extend_from_slice(&(-2i32).to_le_bytes());
+    second_packet.extend_from_slice(&answer_id.to_le_bytes());
+    second_packet.extend_from_slice(&[0x02, 0x01, 0xE0, 0x04]);
+    second_packet.extend_from_slice(second);
+
+    vec![first_packet, second_packet]
+}
+
+fn spawn_server(response_packets: Vec<Vec<u8>>) -> std::net::SocketAddr {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    thread::spawn(move || {
+        let mut buf = [0u8; 25];
+
+        let (_, client_addr) = server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive rules request");
+        for packet in response_packets {
+            server_socket
+                .send_to(&packet, client_addr)
+                .expect("Failed to send rules response");
+        }
+    });
+
+    thread::sleep(Duration::from_millis(10));
+
+    server_addr
+}
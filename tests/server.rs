@@ -0,0 +1,177 @@
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use a2s_query::errors::Error;
+use a2s_query::info::{Info, ServerEnvironment, ServerType, SourceTV};
+use a2s_query::players::Player;
+use a2s_query::rules::Rule;
+use a2s_query::server::Responder;
+use a2s_query::{A2S, Engine};
+
+const PEER: &str = "127.0.0.1:40000";
+
+#[test]
+fn test_server_info_players_rules() {
+    let responder = Responder::new(test_info())
+        .with_players(test_players())
+        .with_rules(test_rules(2));
+    let a2s = A2S::new(spawn_responder(responder));
+
+    assert_eq!(a2s.info().expect("Failed to get info"), test_info());
+    assert_eq!(
+        a2s.players().expect("Failed to get players"),
+        test_players()
+    );
+    assert_rules(a2s.rules().expect("Failed to get rules"), 2);
+}
+
+#[test]
+fn test_server_compressed_multi_packet() {
+    let responder = Responder::new(test_info())
+        .with_rules(test_rules(500))
+        .with_max_packet_size(400)
+        .with_compression(true);
+    let a2s = A2S::new(spawn_responder(responder));
+
+    let outcome = a2s.rules_outcome().expect("Failed to get rules");
+    assert!(outcome.split);
+    assert!(outcome.compressed);
+    assert_rules(outcome.value, 500);
+}
+
+#[test]
+fn test_server_goldsource_multi_packet() {
+    let responder = Responder::new(test_info())
+        .with_rules(test_rules(100))
+        .with_engine(Engine::GoldSource)
+        .with_compression(true);
+    let a2s = A2S::new(spawn_responder(responder)).with_engine(Engine::GoldSource);
+
+    let outcome = a2s.rules_outcome().expect("Failed to get rules");
+    assert!(outcome.split);
+    assert!(!outcome.compressed);
+    assert_rules(outcome.value, 100);
+}
+
+#[test]
+fn test_server_challenge() {
+    let mut responder = Responder::new(test_info()).with_rules(test_rules(1));
+    let peer: SocketAddr = PEER.parse().unwrap();
+
+    let request = [0xFF, 0xFF, 0xFF, 0xFF, 0x56, 0xFF, 0xFF, 0xFF, 0xFF];
+    let datagrams = responder.handle(&request, peer).unwrap();
+    assert_eq!(datagrams.len(), 1);
+    assert_eq!(datagrams[0][..5], [0xFF, 0xFF, 0xFF, 0xFF, 0x41]);
+
+    let mut wrong_challenge = request;
+    wrong_challenge[5..].copy_from_slice(&datagrams[0][5..]);
+    wrong_challenge[5] ^= 1;
+    assert_eq!(responder.handle(&wrong_challenge, peer).unwrap(), datagrams);
+
+    let mut answered = request;
+    answered[5..].copy_from_slice(&datagrams[0][5..]);
+    let datagrams = responder.handle(&answered, peer).unwrap();
+    assert_eq!(datagrams[0][..5], [0xFF, 0xFF, 0xFF, 0xFF, 0x45]);
+}
+
+#[test]
+fn test_server_info_without_challenge() {
+    let mut responder = Responder::new(test_info()).with_info_challenge(false);
+    let mut request = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x54];
+    request.extend_from_slice(b"Source Engine Query\0");
+
+    let datagrams = responder.handle(&request, PEER.parse().unwrap()).unwrap();
+    assert_eq!(datagrams.len(), 1);
+    assert_eq!(datagrams[0][..5], [0xFF, 0xFF, 0xFF, 0xFF, 0x49]);
+}
+
+#[test]
+fn test_server_invalid_request() {
+    let mut responder = Responder::new(test_info());
+    let peer = PEER.parse().unwrap();
+
+    let unknown = [0xFF, 0xFF, 0xFF, 0xFF, 0x69];
+    assert!(matches!(
+        responder.handle(&unknown, peer),
+        Err(Error::InvalidRequest)
+    ));
+    let split = [0xFE, 0xFF, 0xFF, 0xFF, 0x56, 0xFF, 0xFF, 0xFF, 0xFF];
+    assert!(matches!(
+        responder.handle(&split, peer),
+        Err(Error::InvalidRequest)
+    ));
+}
+
+fn test_info() -> Info {
+    Info {
+        protocol: 17,
+        name: "Rust Mod Host".to_string(),
+        map: "de_dust2".to_string(),
+        folder: "cstrike".to_string(),
+        game: "Counter-Strike: Source".to_string(),
+        app_id: 240,
+        players: 2,
+        max_players: 24,
+        bots: 0,
+        server_type: ServerType::Dedicated,
+        environment: ServerEnvironment::Linux,
+        password: false,
+        vac: true,
+        the_ship: None,
+        version: "1.0.0.0".to_string(),
+        edf: 0xE0,
+        port: Some(27015),
+        steam_id: None,
+        sourcetv_info: Some(SourceTV {
+            port: 27020,
+            name: "SourceTV".to_string(),
+        }),
+        keywords: Some("rust,mods".to_string()),
+        game_id: None,
+        goldsrc: None,
+    }
+}
+
+fn test_players() -> Vec<Player> {
+    ["Killer", "Victim"]
+        .iter()
+        .enumerate()
+        .map(|(index, name)| Player {
+            index: index as u8,
+            name: name.to_string(),
+            score: 5 - index as i32,
+            duration: 434.5,
+            the_ship: None,
+        })
+        .collect()
+}
+
+fn test_rules(n_rules: usize) -> Vec<Rule> {
+    (0..n_rules)
+        .map(|i| Rule {
+            name: format!("sv_rule_{i}"),
+            value: i.to_string(),
+        })
+        .collect()
+}
+
+fn assert_rules(rules: Vec<Rule>, n_rules: usize) {
+    assert_eq!(rules.len(), n_rules);
+    for (i, rule) in rules.iter().enumerate() {
+        assert_eq!(rule.name, format!("sv_rule_{i}"));
+        assert_eq!(rule.value, i.to_string());
+    }
+}
+
+/// Answer requests until none is received for a while
+fn spawn_responder(mut responder: Responder) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || while responder.serve_one(&socket).is_ok() {});
+    address
+}
@@ -0,0 +1,171 @@
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use a2s_query::server::Responder;
+use a2s_query::{A2S, ScriptedTransport};
+
+mod common;
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+const PLAYERS_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x00, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05, 0x00,
+    0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+];
+const RULES_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x45, 0x01, 0x00, 0x73, 0x76, 0x5F, 0x63, 0x68, 0x65, 0x61, 0x74, 0x73,
+    0x00, 0x30, 0x00,
+];
+
+#[test]
+fn test_query_all_shares_challenge() {
+    let a2s = A2S::builder(spawn_server(true))
+        .timeout(Duration::from_millis(500))
+        .build();
+
+    let snapshot = a2s.query_all();
+    assert_eq!(
+        snapshot.info.expect("Failed to get info").name,
+        "Sensemann SiN DM"
+    );
+    assert_eq!(
+        snapshot.players.expect("Failed to get players")[0].name,
+        "Killer"
+    );
+    assert_eq!(
+        snapshot.rules.expect("Failed to get rules")[0].name,
+        "sv_cheats"
+    );
+}
+
+#[test]
+fn test_query_all_rules_disabled() {
+    let a2s = A2S::builder(spawn_server(false))
+        .timeout(Duration::from_millis(300))
+        .build();
+
+    let snapshot = a2s.query_all();
+    assert_eq!(
+        snapshot.info.expect("Failed to get info").name,
+        "Sensemann SiN DM"
+    );
+    assert_eq!(
+        snapshot.players.expect("Failed to get players")[0].name,
+        "Killer"
+    );
+    assert!(snapshot.rules.expect_err("Rules should fail").is_timeout());
+}
+
+#[test]
+fn test_query_all_batch_retry() {
+    // The first batch gets a new challenge back and nothing else, the retry every response
+    let info = common::sin_dm_info().encode();
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[CHALLENGE_RESPONSE])
+        .drop_request()
+        .drop_request()
+        .reply(&[&info])
+        .reply(&[PLAYERS_RESPONSE])
+        .reply(&[RULES_RESPONSE]);
+    let a2s = A2S::builder("127.0.0.1:27015")
+        .retries(1)
+        .backoff(Duration::ZERO)
+        .build()
+        .with_transport(transport.clone());
+
+    let snapshot = a2s.query_all();
+    assert!(snapshot.info.is_ok());
+    assert!(snapshot.players.is_ok());
+    assert_eq!(
+        snapshot.rules.expect("Failed to get rules")[0].name,
+        "sv_cheats"
+    );
+    assert_eq!(transport.requests().len(), 7);
+}
+
+#[test]
+fn test_query_all_batch_falls_back_to_next_address() {
+    let silent_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    let silent_addr = silent_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    // Drop the challenge request, then answer everything
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+    let mut responder = Responder::new(common::sin_dm_info());
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+        server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive challenge request");
+        while responder.serve_one(&server_socket).is_ok() {}
+    });
+
+    let addresses = [silent_addr, server_addr];
+    let a2s = A2S::builder(&addresses[..])
+        .timeout(Duration::from_millis(600))
+        .build();
+
+    let snapshot = a2s.query_all();
+    assert!(
+        snapshot
+            .players
+            .expect_err("Players should fail")
+            .is_timeout()
+    );
+    assert_eq!(
+        snapshot.info.expect("Failed to get info").name,
+        "Sensemann SiN DM"
+    );
+    assert!(snapshot.rules.expect("Failed to get rules").is_empty());
+    assert_eq!(a2s.peer_addr(), Some(server_addr));
+}
+
+/// Hand out a challenge, then answer the requests carrying it according to their header
+fn spawn_server(rules_enabled: bool) -> std::net::SocketAddr {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").expect("Failed to bind server socket");
+    server_socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .expect("Failed to set read timeout");
+    let server_addr = server_socket
+        .local_addr()
+        .expect("Failed to get local address");
+
+    let info = common::sin_dm_info().encode();
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+
+        let (_, client_addr) = server_socket
+            .recv_from(&mut buf)
+            .expect("Failed to receive challenge request");
+        server_socket
+            .send_to(CHALLENGE_RESPONSE, client_addr)
+            .expect("Failed to send challenge");
+
+        for _ in 0..3 {
+            let (n, client_addr) = server_socket
+                .recv_from(&mut buf)
+                .expect("Failed to receive request");
+            assert_eq!(buf[n - 4..n], CHALLENGE_RESPONSE[5..]);
+
+            let response = match buf[4] {
+                0x54 => &info[..],
+                0x55 => PLAYERS_RESPONSE,
+                0x56 if rules_enabled => RULES_RESPONSE,
+                _ => continue,
+            };
+            server_socket
+                .send_to(response, client_addr)
+                .expect("Failed to send response");
+        }
+    });
+
+    server_addr
+}
@@ -0,0 +1,67 @@
+use a2s_query::steam::{AccountType, GameId, GameIdType, SteamId};
+
+#[test]
+fn test_steam_id_individual() {
+    let steam_id: SteamId = "STEAM_0:1:11101".parse().expect("Failed to parse SteamID");
+    assert_eq!(steam_id.as_u64(), 76561197960287931);
+    assert_eq!(steam_id.universe(), 1);
+    assert_eq!(steam_id.account_type(), AccountType::Individual);
+    assert_eq!(steam_id.instance(), 1);
+    assert_eq!(steam_id.account_id(), 22203);
+    assert_eq!(steam_id.to_string(), "[U:1:22203]");
+    assert_eq!(steam_id.to_steam2().as_deref(), Some("STEAM_1:1:11101"));
+    assert_eq!("[U:1:22203]".parse::<SteamId>().unwrap(), steam_id);
+    assert_eq!("76561197960287931".parse::<SteamId>().unwrap(), steam_id);
+}
+
+#[test]
+fn test_steam_id_game_server() {
+    let steam_id = SteamId::from(85568392920162880);
+    assert_eq!(steam_id.account_type(), AccountType::GameServer);
+    assert_eq!(steam_id.instance(), 0);
+    assert_eq!(steam_id.account_id(), 123456);
+    assert_eq!(steam_id.to_string(), "[G:1:123456]");
+    assert_eq!(steam_id.to_steam2(), None);
+    assert_eq!("[G:1:123456]".parse::<SteamId>().unwrap(), steam_id);
+
+    let anonymous = SteamId::new(1, AccountType::AnonGameServer, 5, 123456);
+    assert_eq!(anonymous.as_u64(), 90072014022369856);
+    assert_eq!(anonymous.to_string(), "[A:1:123456:5]");
+    assert_eq!("[A:1:123456:5]".parse::<SteamId>().unwrap(), anonymous);
+}
+
+#[test]
+fn test_steam_id_invalid() {
+    for s in [
+        "",
+        "STEAM_0:2:1",
+        "STEAM_0:1",
+        "[X:1:2]",
+        "[U:1:2:3:4]",
+        "U:1:2",
+    ] {
+        assert!(s.parse::<SteamId>().is_err(), "{s} should not parse");
+    }
+}
+
+#[test]
+fn test_steam_id_json() {
+    let steam_id = SteamId::from(85568392920162880);
+    let json = serde_json::to_string(&steam_id).unwrap();
+    assert_eq!(json, "85568392920162880");
+    assert_eq!(serde_json::from_str::<SteamId>(&json).unwrap(), steam_id);
+}
+
+#[test]
+fn test_game_id() {
+    let game_id = GameId::from(30081548608);
+    assert_eq!(game_id.app_id(), 320);
+    assert_eq!(game_id.kind(), GameIdType::GameMod);
+    assert_eq!(game_id.mod_id(), 7);
+
+    let game_id = GameId::from(730);
+    assert_eq!(game_id.app_id(), 730);
+    assert_eq!(game_id.kind(), GameIdType::App);
+    assert_eq!(game_id.mod_id(), 0);
+    assert_eq!(serde_json::to_string(&game_id).unwrap(), "730");
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use a2s_query::info::Info;
+use a2s_query::{A2S, Quirks, ScriptedTransport};
+
+mod common;
+
+const CHALLENGE_RESPONSE: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x41, 0x0A, 0x08, 0x5E, 0xEA];
+const PLAYERS_RESPONSE: &[u8] = &[
+    0xFF, 0xFF, 0xFF, 0xFF, 0x44, 0x01, 0x00, 0x4B, 0x69, 0x6C, 0x6C, 0x65, 0x72, 0x00, 0x05, 0x00,
+    0x00, 0x00, 0x69, 0x24, 0xD9, 0x43,
+];
+// Rules split in two Source packets of answer ID 7
+const RULES_PACKET_0: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0xE0, 0x04, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x45, 0x01, 0x00, 0x73, 0x76, 0x5F,
+];
+const RULES_PACKET_1: &[u8] = &[
+    0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02, 0x01, 0xE0, 0x04, 0x63, 0x68, 0x65, 0x61,
+    0x74, 0x73, 0x00, 0x30, 0x00,
+];
+
+#[test]
+fn test_transport_players_challenge() {
+    let transport = ScriptedTransport::new()
+        .reply(&[&common::sin_dm_info().encode()])
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[PLAYERS_RESPONSE]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport.clone());
+
+    let players = a2s.players().expect("Failed to get players");
+    assert_eq!(players[0].name, "Killer");
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(
+        requests[1],
+        [0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0xFF, 0xFF, 0xFF, 0xFF]
+    );
+    assert_eq!(
+        requests[2],
+        [0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0x0A, 0x08, 0x5E, 0xEA]
+    );
+}
+
+#[test]
+fn test_transport_retry_after_dropped_request() {
+    let transport = ScriptedTransport::new()
+        .drop_request()
+        .reply(&[&common::sin_dm_info().encode()]);
+    let a2s = A2S::builder("127.0.0.1:27015")
+        .retries(1)
+        .backoff(Duration::ZERO)
+        .build()
+        .with_transport(transport.clone());
+
+    let info = a2s.info().expect("Failed to get info");
+    assert_eq!(info.name, "Sensemann SiN DM");
+    assert_eq!(transport.requests().len(), 2);
+}
+
+#[test]
+fn test_transport_reordered_packets() {
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[RULES_PACKET_1, RULES_PACKET_0]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].name, "sv_cheats");
+    assert_eq!(rules[0].value, "0");
+}
+
+#[test]
+fn test_transport_unanswered() {
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(ScriptedTransport::new());
+
+    assert!(
+        a2s.info()
+            .expect_err("Request should time out")
+            .is_timeout()
+    );
+}
+
+#[test]
+fn test_transport_quirks_from_app_id() {
+    // Info of app ID 215, whose split headers lack the size field
+    let info = Info {
+        app_id: 215,
+        ..common::sin_dm_info()
+    }
+    .encode();
+    let packets = split_packets_without_size(PLAYERS_RESPONSE);
+
+    let transport = ScriptedTransport::new()
+        .reply(&[&info])
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[&packets[1], &packets[0]]);
+    let a2s = A2S::new("127.0.0.1:27015").with_transport(transport);
+
+    let players = a2s.players().expect("Failed to get players");
+    assert_eq!(players[0].name, "Killer");
+    assert_eq!(a2s.app_id(), Some(215));
+}
+
+#[test]
+fn test_transport_explicit_quirks() {
+    let mut payload = RULES_PACKET_0[12..].to_vec();
+    payload.extend_from_slice(&RULES_PACKET_1[12..]);
+    let packets = split_packets_without_size(&payload);
+
+    let transport = ScriptedTransport::new()
+        .reply(&[CHALLENGE_RESPONSE])
+        .reply(&[&packets[0], &packets[1]]);
+    let a2s = A2S::new("127.0.0.1:27015")
+        .with_quirks(Quirks {
+            split_size_field: false,
+        })
+        .with_transport(transport);
+
+    let rules = a2s.rules().expect("Failed to get rules");
+    assert_eq!(rules[0].name, "sv_cheats");
+}
+
+/// Split the payload in two Source packets of answer ID 7, without the size field
+fn split_packets_without_size(payload: &[u8]) -> Vec<Vec<u8>> {
+    let (first, second) = payload.split_at(payload.len() / 2);
+    [first, second]
+        .iter()
+        .enumerate()
+        .map(|(number, chunk)| {
+            let mut packet = vec![0xFE, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x00, 0x00, 0x02];
+            packet.push(number as u8);
+            packet.extend_from_slice(chunk);
+            packet
+        })
+        .collect()
+}